@@ -0,0 +1,234 @@
+use std::f32::consts::TAU;
+
+use glam::Vec2;
+
+use crate::mesh::Vertex;
+use crate::utils::Rng;
+
+pub const ASTEROID_COLOR: [f32; 3] = [0.85, 0.85, 0.85];
+
+/// Size classes of asteroids
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AsteroidSize {
+    Large,
+    Medium,
+    Small,
+}
+
+impl AsteroidSize {
+    /// Outline parameters for the size class, bigger asteroids have more vertices
+    pub fn params(self) -> OutlineParams {
+        let (vertex_count, radius) = match self {
+            AsteroidSize::Large => (12, 48.0),
+            AsteroidSize::Medium => (10, 24.0),
+            AsteroidSize::Small => (8, 12.0),
+        };
+        OutlineParams {
+            vertex_count,
+            radius,
+            jaggedness: 0.4,
+        }
+    }
+}
+
+/// Parameters of the asteroid outline generator
+#[derive(Clone, Copy, Debug)]
+pub struct OutlineParams {
+    /// Number of vertices around the outline, at least 3
+    pub vertex_count: u16,
+    /// Distance of the outermost vertices to the center
+    pub radius: f32,
+    /// Fraction of the radius vertices can sink towards the center, in [0, 1)
+    pub jaggedness: f32,
+}
+
+/// A closed irregular outline around the origin, with the mesh to draw it and
+/// the polygon to collide with it
+pub struct AsteroidShape {
+    pub vertices: Vec<Vertex>,
+    /// Line list closing the outline
+    pub indices: Vec<u16>,
+    /// Outline vertices in order, the same the mesh is made of
+    pub polygon: Vec<Vec2>,
+    /// Distance of the farthest vertex to the center, for broad collision checks
+    pub radius: f32,
+}
+
+impl AsteroidShape {
+    /// Generates an outline by walking around the center at jittered angles,
+    /// with every vertex sunk by a random amount. The same seed and parameters
+    /// always give the same outline.
+    pub fn generate(params: &OutlineParams, seed: u32) -> Self {
+        let mut rng = Rng::new(seed);
+        let count = params.vertex_count.max(3);
+        let jaggedness = params.jaggedness.clamp(0.0, 0.95);
+        // Angles are jittered by less than half a step so vertices stay in order
+        let step = TAU / count as f32;
+        let polygon: Vec<Vec2> = (0..count)
+            .map(|i| {
+                let angle = step * (i as f32 + rng.range(-0.4, 0.4));
+                let distance = params.radius * (1.0 - jaggedness * rng.next_f32());
+                Vec2::from_angle(angle) * distance
+            })
+            .collect();
+
+        let vertices = polygon
+            .iter()
+            .map(|point| Vertex {
+                position: *point,
+                color: ASTEROID_COLOR,
+            })
+            .collect();
+        let indices = (0..count).flat_map(|i| [i, (i + 1) % count]).collect();
+        let radius = polygon
+            .iter()
+            .map(|point| point.length())
+            .fold(0.0, f32::max);
+        Self {
+            vertices,
+            indices,
+            polygon,
+            radius,
+        }
+    }
+
+    /// Several differently seeded outlines for a size class, so a field doesn't
+    /// look uniform
+    pub fn variants(size: AsteroidSize, count: u32, seed: u32) -> Vec<Self> {
+        let params = size.params();
+        (0..count)
+            .map(|variant| {
+                Self::generate(
+                    &params,
+                    seed.wrapping_add(variant.wrapping_mul(0x9e37_79b9)),
+                )
+            })
+            .collect()
+    }
+
+    /// Whether a point in the asteroid's model space is inside its outline
+    pub fn contains(&self, point: Vec2) -> bool {
+        if point.length_squared() > self.radius * self.radius {
+            return false;
+        }
+        // Even-odd rule, counting the edges crossed by a ray towards +x
+        let mut inside = false;
+        for (i, a) in self.polygon.iter().enumerate() {
+            let b = self.polygon[(i + 1) % self.polygon.len()];
+            if (a.y > point.y) != (b.y > point.y) {
+                let crossing = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if point.x < crossing {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAMS: OutlineParams = OutlineParams {
+        vertex_count: 10,
+        radius: 30.0,
+        jaggedness: 0.5,
+    };
+
+    #[test]
+    fn same_seed_same_outline() {
+        let a = AsteroidShape::generate(&PARAMS, 42);
+        let b = AsteroidShape::generate(&PARAMS, 42);
+        assert_eq!(a.polygon, b.polygon);
+        assert_eq!(a.indices, b.indices);
+        assert_ne!(a.polygon, AsteroidShape::generate(&PARAMS, 43).polygon);
+    }
+
+    #[test]
+    fn variants_differ() {
+        let variants = AsteroidShape::variants(AsteroidSize::Medium, 3, 7);
+        assert_eq!(variants.len(), 3);
+        for (i, a) in variants.iter().enumerate() {
+            for b in &variants[i + 1..] {
+                assert_ne!(a.polygon, b.polygon);
+            }
+        }
+        // The first variant is the one generated from the seed itself
+        assert_eq!(
+            variants[0].polygon,
+            AsteroidShape::generate(&AsteroidSize::Medium.params(), 7).polygon
+        );
+    }
+
+    #[test]
+    fn closed_outline_within_radius() {
+        let shape = AsteroidShape::generate(&PARAMS, 1234);
+        let count = PARAMS.vertex_count;
+        assert_eq!(shape.polygon.len(), count as usize);
+        assert_eq!(shape.vertices.len(), count as usize);
+        // Every vertex starts one line and ends another, the last back to the first
+        let lines: Vec<[u16; 2]> = shape
+            .indices
+            .chunks_exact(2)
+            .map(|line| [line[0], line[1]])
+            .collect();
+        assert_eq!(lines.len(), count as usize);
+        assert!((0..count).all(|i| lines.contains(&[i, (i + 1) % count])));
+
+        let min_radius = PARAMS.radius * (1.0 - PARAMS.jaggedness);
+        for point in &shape.polygon {
+            let distance = point.length();
+            assert!(distance >= min_radius - 1e-4 && distance <= PARAMS.radius + 1e-4);
+            assert!(distance <= shape.radius);
+        }
+        assert!(shape.radius <= PARAMS.radius + 1e-4);
+    }
+
+    #[test]
+    fn vertices_in_angular_order() {
+        let shape = AsteroidShape::generate(&PARAMS, 99);
+        let angles: Vec<f32> = shape
+            .polygon
+            .iter()
+            .map(|point| point.y.atan2(point.x).rem_euclid(TAU))
+            .collect();
+        // The first vertex may be jittered below 0, wrapping it to the end
+        assert!(angles[1..].windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn contains_points() {
+        let shape = AsteroidShape::generate(&PARAMS, 5);
+        let min_radius = PARAMS.radius * (1.0 - PARAMS.jaggedness);
+        assert!(shape.contains(Vec2::ZERO));
+        for i in 0..16 {
+            let direction = Vec2::from_angle(TAU * i as f32 / 16.0);
+            // Every vertex is at least this far, so closer points are inside
+            assert!(shape.contains(direction * min_radius * 0.5));
+            assert!(!shape.contains(direction * (shape.radius + 1.0)));
+        }
+        // Right outside a vertex, away from the center
+        let vertex = shape.polygon[0];
+        assert!(!shape.contains(vertex * 1.05));
+        assert!(shape.contains(vertex * 0.5));
+    }
+
+    #[test]
+    fn degenerate_params() {
+        let shape = AsteroidShape::generate(
+            &OutlineParams {
+                vertex_count: 1,
+                radius: 10.0,
+                jaggedness: 2.0,
+            },
+            0,
+        );
+        // At least a triangle, and no vertex collapses on the center
+        assert_eq!(shape.polygon.len(), 3);
+        assert!(shape
+            .polygon
+            .iter()
+            .all(|point| point.length() >= 0.5 - 1e-4));
+    }
+}
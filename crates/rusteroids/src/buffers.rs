@@ -0,0 +1,231 @@
+use std::ops::{Deref, Range};
+
+/// A GPU buffer whose contents can be replaced at runtime. Writes that don't fit
+/// reallocate it, doubling its capacity so a growing mesh reallocates rarely.
+pub struct DynamicBuffer {
+    buffer: wgpu::Buffer,
+    label: String,
+    usage: wgpu::BufferUsages,
+}
+
+impl DynamicBuffer {
+    /// Creates a buffer holding contents, usage is extended with COPY_DST
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        usage: wgpu::BufferUsages,
+        contents: &[u8],
+    ) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        Self {
+            buffer: create(device, label, usage, contents.len() as u64, contents),
+            label: label.to_string(),
+            usage,
+        }
+    }
+
+    /// Replaces the contents, reallocating when they don't fit. Any BufferSlice
+    /// taken before has to be taken again afterwards.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, contents: &[u8]) {
+        match plan_write(self.buffer.size(), contents.len() as u64) {
+            Write::Grow(capacity) => {
+                log::debug!("Growing {} to {capacity} bytes", self.label);
+                self.buffer = create(device, &self.label, self.usage, capacity, contents);
+            }
+            Write::InPlace(len) if len == contents.len() as u64 => {
+                queue.write_buffer(&self.buffer, 0, contents);
+            }
+            Write::InPlace(len) => {
+                let mut padded = contents.to_vec();
+                padded.resize(len as usize, 0);
+                queue.write_buffer(&self.buffer, 0, &padded);
+            }
+            Write::Skip => {}
+        }
+    }
+}
+
+impl Deref for DynamicBuffer {
+    type Target = wgpu::Buffer;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+/// Allocates the data of many small meshes that change every frame from a single
+/// buffer, instead of a buffer each. Data is pushed during the frame and uploaded
+/// at once before rendering, then cleared for the next frame. The queue orders the
+/// upload after the previous frame's commands, so the same space is safely reused.
+pub struct StreamingBuffer<T: bytemuck::Pod> {
+    buffer: DynamicBuffer,
+    staging: Vec<T>,
+}
+
+impl<T: bytemuck::Pod> StreamingBuffer<T> {
+    /// Creates the buffer with room for capacity elements, it grows when a frame
+    /// pushes more
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        usage: wgpu::BufferUsages,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            buffer: DynamicBuffer::new(
+                device,
+                label,
+                usage,
+                &vec![0; capacity * std::mem::size_of::<T>()],
+            ),
+            staging: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Appends data for this frame, returning the range of elements it will occupy
+    pub fn push(&mut self, data: &[T]) -> Range<u32> {
+        append(&mut self.staging, data)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.staging.is_empty()
+    }
+
+    /// Uploads everything pushed this frame, growing the buffer if needed
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.staging.is_empty() {
+            self.buffer
+                .write(device, queue, bytemuck::cast_slice(&self.staging));
+        }
+    }
+
+    /// Frees the space for the next frame
+    pub fn clear(&mut self) {
+        self.staging.clear();
+    }
+}
+
+impl<T: bytemuck::Pod> Deref for StreamingBuffer<T> {
+    type Target = wgpu::Buffer;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+/// How contents are written into a buffer
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Write {
+    /// Into a new buffer of this capacity, as they don't fit
+    Grow(u64),
+    /// Over the start of the buffer, padded to this many bytes
+    InPlace(u64),
+    /// Nothing to write
+    Skip,
+}
+
+/// How len bytes of contents are written into a buffer of size bytes
+fn plan_write(size: u64, len: u64) -> Write {
+    if len > size {
+        Write::Grow(len.next_power_of_two())
+    } else if len > 0 {
+        // Writes must be 4 byte aligned, as happens with an odd number of u16
+        Write::InPlace(align(len))
+    } else {
+        Write::Skip
+    }
+}
+
+/// Appends data to what was staged this frame, returning the range of elements
+/// it occupies
+fn append<T: Copy>(staging: &mut Vec<T>, data: &[T]) -> Range<u32> {
+    let start = staging.len() as u32;
+    staging.extend_from_slice(data);
+    start..staging.len() as u32
+}
+
+fn align(size: u64) -> u64 {
+    size.div_ceil(wgpu::COPY_BUFFER_ALIGNMENT) * wgpu::COPY_BUFFER_ALIGNMENT
+}
+
+/// Creates a buffer of at least capacity bytes starting with contents. Never
+/// empty, as empty buffers can't be bound.
+fn create(
+    device: &wgpu::Device,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    capacity: u64,
+    contents: &[u8],
+) -> wgpu::Buffer {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: align(capacity.max(1)),
+        usage,
+        mapped_at_creation: true,
+    });
+    buffer.slice(..).get_mapped_range_mut()[..contents.len()].copy_from_slice(contents);
+    buffer.unmap();
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_to_powers_of_two() {
+        assert_eq!(plan_write(16, 20), Write::Grow(32));
+        assert_eq!(plan_write(32, 33), Write::Grow(64));
+        assert_eq!(plan_write(64, 64), Write::InPlace(64));
+        // Buffers are at least 4 bytes, the capacity is aligned when created
+        assert_eq!(plan_write(4, 6), Write::Grow(8));
+        assert_eq!(align(plan_write(0, 3).into_capacity()), 4);
+    }
+
+    #[test]
+    fn pads_unaligned_writes() {
+        // Three u16 indices
+        assert_eq!(plan_write(8, 6), Write::InPlace(8));
+        assert_eq!(plan_write(64, 10), Write::InPlace(12));
+        assert_eq!(plan_write(64, 0), Write::Skip);
+    }
+
+    #[test]
+    fn offsets_across_pushes() {
+        let mut staging = Vec::new();
+        assert_eq!(append(&mut staging, &[1, 2, 3]), 0..3);
+        assert_eq!(append(&mut staging, &[4, 5]), 3..5);
+        assert_eq!(append(&mut staging, &[]), 5..5);
+        assert_eq!(append(&mut staging, &[6, 7, 8, 9]), 5..9);
+        assert_eq!(staging, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn reuses_space_after_clear() {
+        // A frame streaming two 12 byte meshes into a 16 byte buffer
+        let mut size = 16;
+        let mut staging: Vec<[f32; 3]> = Vec::new();
+        append(&mut staging, &[[0.0; 3]]);
+        append(&mut staging, &[[1.0; 3]]);
+        let write = plan_write(size, size_of_val(staging.as_slice()) as u64);
+        assert_eq!(write, Write::Grow(32));
+        size = write.into_capacity();
+
+        // The next frame starts over from the start of the grown buffer
+        staging.clear();
+        assert_eq!(append(&mut staging, &[[2.0; 3]]), 0..1);
+        assert_eq!(
+            plan_write(size, size_of_val(staging.as_slice()) as u64),
+            Write::InPlace(12)
+        );
+    }
+
+    impl Write {
+        fn into_capacity(self) -> u64 {
+            match self {
+                Write::Grow(capacity) => capacity,
+                _ => panic!("{self:?} doesn't grow the buffer"),
+            }
+        }
+    }
+}
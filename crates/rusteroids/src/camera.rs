@@ -1,68 +1,254 @@
-use glam::Mat4;
+use glam::{uvec2, vec2, Mat4, UVec2, Vec2, Vec3};
 use wgpu::RenderPass;
+use wgpu_utils::{UniformLayout, WgslStruct};
 
-use crate::utils::{common_layout_descriptor, Bindable, UniformBuffer};
+use crate::utils::UniformBuffer;
 
-const GL_TO_WGPU: Mat4 = Mat4::from_cols(
-    glam::vec4(1.0, 0.0, 0.0, 0.0),
-    glam::vec4(0.0, 1.0, 0.0, 0.0),
-    glam::vec4(0.0, 0.0, 0.5, 0.0),
-    glam::vec4(0.0, 0.0, 0.5, 1.0),
-);
+/// The camera as read by the shaders
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, UniformLayout, WgslStruct)]
+pub struct CameraUniform {
+    pub view_proj: Mat4,
+}
 
-// TODO: convert this to trait to enable Perspective cameras as well
-pub struct OrthoCamera {
-    width: u32,
-    height: u32,
-    uniform: Option<UniformBuffer>,
+// Screen shake at full trauma, offset in world units and rotation in radians
+const MAX_SHAKE_OFFSET: f32 = 12.0;
+const MAX_SHAKE_ANGLE: f32 = 0.05;
+// Trauma lost per second
+const TRAUMA_DECAY: f32 = 1.2;
+// Exponential decay rate of the zoom punch, per second
+const PUNCH_DECAY: f32 = 10.0;
+
+/// Where the world origin sits on screen, and which way y points
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Origin {
+    /// Origin at the center of the screen, y pointing up
+    Center,
+    /// Origin at the top left corner of the screen, y pointing down, like window coordinates
+    TopLeft,
 }
 
-impl Bindable for OrthoCamera {
-    fn layout_desc<'a>() -> wgpu::BindGroupLayoutDescriptor<'a> {
-        common_layout_descriptor(Some("mat4_layout_descriptor"))
+/// A camera providing view and projection matrices, uploaded together as a
+/// single view projection uniform bound to group 0
+pub trait Camera {
+    fn view_matrix(&self) -> Mat4;
+
+    /// Column-major like every glam and WGSL matrix, so shaders multiply it on
+    /// the left of column vectors. Depth is mapped to wgpu's [0, 1] range.
+    fn proj_matrix(&self) -> Mat4;
+
+    /// Updates the projection to the new surface size, uploading it if it changed
+    fn resize(&mut self, width: u32, height: u32, queue: &wgpu::Queue);
+
+    /// Surface size in pixels
+    fn size(&self) -> UVec2;
+
+    /// The area a fixed size playfield is drawn into, if the camera has one
+    fn letterbox(&self) -> Option<Letterbox> {
+        None
+    }
+
+    /// Converts a position in window pixels, y pointing down, to world coordinates
+    /// on the z = 0 plane, for mouse and touch input. Camera effects are ignored.
+    /// Returns None if the view ray is parallel to the plane.
+    fn window_to_world(&self, position: Vec2) -> Option<Vec2> {
+        let size = self.size().max(UVec2::ONE).as_vec2();
+        let ndc = vec2(
+            2.0 * position.x / size.x - 1.0,
+            1.0 - 2.0 * position.y / size.y,
+        );
+        let inverse = self.view_proj_matrix().inverse();
+        let near = inverse.project_point3(ndc.extend(0.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+        let direction = far - near;
+        if direction.z.abs() <= f32::EPSILON {
+            return None;
+        }
+        Some((near - direction * near.z / direction.z).truncate())
+    }
+
+    fn uniform(&self) -> Option<&UniformBuffer<CameraUniform>>;
+
+    fn set_uniform(&mut self, uniform: UniformBuffer<CameraUniform>);
+
+    fn view_proj_matrix(&self) -> Mat4 {
+        self.view_proj_matrix_with(Mat4::IDENTITY)
+    }
+
+    /// The view projection with an effect transform applied in view space,
+    /// on top of the view matrix
+    fn view_proj_matrix_with(&self, effect: Mat4) -> Mat4 {
+        self.proj_matrix()
+            .mul_mat4(&effect)
+            .mul_mat4(&self.view_matrix())
+    }
+
+    fn setup(&mut self, device: &wgpu::Device, bind_group_layout: &wgpu::BindGroupLayout) {
+        self.set_uniform(UniformBuffer::new(
+            &CameraUniform {
+                view_proj: self.view_proj_matrix(),
+            },
+            device,
+            bind_group_layout,
+            "camera",
+        ))
+    }
+
+    fn update_buffer(&self, queue: &wgpu::Queue) {
+        self.update_buffer_with(queue, Mat4::IDENTITY);
+    }
+
+    /// Uploads the view projection with an effect transform, see CameraEffects
+    fn update_buffer_with(&self, queue: &wgpu::Queue, effect: Mat4) {
+        if let Some(uniform) = self.uniform() {
+            uniform.update_buffer(
+                &CameraUniform {
+                    view_proj: self.view_proj_matrix_with(effect),
+                },
+                queue,
+            );
+        }
+    }
+
+    fn bind_group(&self, pass: &mut RenderPass<'_>) {
+        if let Some(uniform) = self.uniform() {
+            uniform.bind(pass, 0);
+        }
     }
 }
 
+/// Enables choosing the camera at runtime
+impl Camera for Box<dyn Camera> {
+    fn view_matrix(&self) -> Mat4 {
+        self.as_ref().view_matrix()
+    }
+
+    fn proj_matrix(&self) -> Mat4 {
+        self.as_ref().proj_matrix()
+    }
+
+    fn resize(&mut self, width: u32, height: u32, queue: &wgpu::Queue) {
+        self.as_mut().resize(width, height, queue)
+    }
+
+    fn size(&self) -> UVec2 {
+        self.as_ref().size()
+    }
+
+    fn letterbox(&self) -> Option<Letterbox> {
+        self.as_ref().letterbox()
+    }
+
+    fn uniform(&self) -> Option<&UniformBuffer<CameraUniform>> {
+        self.as_ref().uniform()
+    }
+
+    fn set_uniform(&mut self, uniform: UniformBuffer<CameraUniform>) {
+        self.as_mut().set_uniform(uniform)
+    }
+}
+
+/// The area of the screen a fixed size playfield is scaled into, keeping its
+/// aspect ratio and leaving bars on the remaining sides
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Letterbox {
+    /// Top left corner in pixels
+    pub x: u32,
+    pub y: u32,
+    /// Size in pixels
+    pub width: u32,
+    pub height: u32,
+    /// Pixels per world unit
+    pub scale: f32,
+}
+
+impl Letterbox {
+    /// The largest centered area with the aspect ratio of playfield fitting the screen
+    pub fn fit(width: u32, height: u32, playfield: Vec2) -> Self {
+        let screen = uvec2(width, height).max(UVec2::ONE);
+        let scale = (screen.as_vec2() / playfield).min_element();
+        let size = (playfield * scale).round().as_uvec2().min(screen);
+        let corner = (screen - size) / 2;
+        Self {
+            x: corner.x,
+            y: corner.y,
+            width: size.x,
+            height: size.y,
+            scale,
+        }
+    }
+}
+
+/// An orthographic camera, either one world unit per pixel or fitting a fixed
+/// size playfield to the screen
+pub struct OrthoCamera {
+    width: u32,
+    height: u32,
+    origin: Origin,
+    playfield: Option<Vec2>,
+    uniform: Option<UniformBuffer<CameraUniform>>,
+}
+
 impl OrthoCamera {
     pub fn new(width: u32, height: u32) -> Self {
+        Self::with_origin(width, height, Origin::Center)
+    }
+
+    pub fn with_origin(width: u32, height: u32, origin: Origin) -> Self {
         Self {
             width,
             height,
+            origin,
+            playfield: None,
             uniform: None,
         }
     }
 
-    pub fn setup(&mut self, device: &wgpu::Device, bind_group_layout: &wgpu::BindGroupLayout) {
-        self.uniform = Some(UniformBuffer::new(
-            &self.proj_matrix(),
-            device,
-            bind_group_layout,
-            "camera",
-        ))
+    /// A camera centered on a playfield of fixed size in world units, scaled to fit
+    /// the screen whatever its size
+    pub fn with_playfield(width: u32, height: u32, playfield: Vec2) -> Self {
+        Self {
+            playfield: Some(playfield),
+            ..Self::new(width, height)
+        }
     }
+}
 
-    pub fn update_buffer(&self, queue: &wgpu::Queue) {
-        if let Some(uniform) = &self.uniform {
-            uniform.update_buffer(&self.proj_matrix(), queue);
+impl Camera for OrthoCamera {
+    /// Moves the origin from the center of the screen if needed
+    fn view_matrix(&self) -> Mat4 {
+        match self.origin {
+            Origin::Center => Mat4::IDENTITY,
+            Origin::TopLeft => Mat4::from_translation(glam::vec3(
+                -(self.width as f32) / 2.0,
+                self.height as f32 / 2.0,
+                0.0,
+            ))
+            .mul_mat4(&Mat4::from_scale(glam::vec3(1.0, -1.0, 1.0))),
         }
     }
 
-    fn proj_matrix(&self) -> [f32; 16] {
-        let (half_width, half_height) = ((self.width / 2) as f32, (self.height / 2) as f32);
-        Mat4::orthographic_lh(
+    fn proj_matrix(&self) -> Mat4 {
+        let (half_width, half_height) = match self.letterbox() {
+            // The whole screen is visible, the bars are cut out afterwards
+            Some(letterbox) => (
+                self.width as f32 / letterbox.scale / 2.0,
+                self.height as f32 / letterbox.scale / 2.0,
+            ),
+            None => ((self.width / 2) as f32, (self.height / 2) as f32),
+        };
+        // Everything is drawn at z = 0, halfway through the depth range
+        Mat4::orthographic_rh(
             -half_width,
             half_width,
             -half_height,
             half_height,
-            0.01,
+            -1000.0,
             1000.0,
         )
-        .mul_mat4(&GL_TO_WGPU)
-        .transpose()
-        .to_cols_array()
     }
 
-    pub fn resize(&mut self, width: u32, height: u32, queue: &wgpu::Queue) {
+    fn resize(&mut self, width: u32, height: u32, queue: &wgpu::Queue) {
         if self.width != width || self.height != height {
             self.width = width;
             self.height = height;
@@ -70,9 +256,331 @@ impl OrthoCamera {
         }
     }
 
-    pub fn bind_group(&self, pass: &mut RenderPass<'_>) {
-        if let Some(uniform) = &self.uniform {
-            uniform.bind(pass, 0);
+    fn size(&self) -> UVec2 {
+        uvec2(self.width, self.height)
+    }
+
+    fn letterbox(&self) -> Option<Letterbox> {
+        self.playfield
+            .map(|playfield| Letterbox::fit(self.width, self.height, playfield))
+    }
+
+    fn uniform(&self) -> Option<&UniformBuffer<CameraUniform>> {
+        self.uniform.as_ref()
+    }
+
+    fn set_uniform(&mut self, uniform: UniformBuffer<CameraUniform>) {
+        self.uniform = Some(uniform);
+    }
+}
+
+/// A perspective camera looking at the playfield from a tilted angle, for a
+/// pseudo 3D look. The distance is chosen so the playfield fits the screen
+/// when the camera isn't tilted.
+pub struct PerspectiveCamera {
+    width: u32,
+    height: u32,
+    playfield: Vec2,
+    /// Vertical field of view in radians
+    fov_y: f32,
+    /// Rotation of the playfield around the x axis in radians, away from the viewer
+    tilt: f32,
+    uniform: Option<UniformBuffer<CameraUniform>>,
+}
+
+impl PerspectiveCamera {
+    pub fn new(width: u32, height: u32, playfield: Vec2, fov_y: f32, tilt: f32) -> Self {
+        Self {
+            width,
+            height,
+            playfield,
+            fov_y,
+            tilt,
+            uniform: None,
         }
     }
+
+    /// Distance to the playfield, so the screen height spans the visible world height
+    fn distance(&self) -> f32 {
+        let scale = Letterbox::fit(self.width, self.height, self.playfield).scale;
+        self.height.max(1) as f32 / scale / 2.0 / (self.fov_y / 2.0).tan()
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn view_matrix(&self) -> Mat4 {
+        let distance = self.distance();
+        let eye = Vec3::new(0.0, -self.tilt.sin(), self.tilt.cos()) * distance;
+        Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y)
+    }
+
+    fn proj_matrix(&self) -> Mat4 {
+        let distance = self.distance();
+        let aspect = self.width.max(1) as f32 / self.height.max(1) as f32;
+        Mat4::perspective_rh(self.fov_y, aspect, distance * 0.1, distance * 10.0)
+    }
+
+    fn resize(&mut self, width: u32, height: u32, queue: &wgpu::Queue) {
+        if self.width != width || self.height != height {
+            self.width = width;
+            self.height = height;
+            self.update_buffer(queue);
+        }
+    }
+
+    fn size(&self) -> UVec2 {
+        uvec2(self.width, self.height)
+    }
+
+    fn letterbox(&self) -> Option<Letterbox> {
+        Some(Letterbox::fit(self.width, self.height, self.playfield))
+    }
+
+    fn uniform(&self) -> Option<&UniformBuffer<CameraUniform>> {
+        self.uniform.as_ref()
+    }
+
+    fn set_uniform(&mut self, uniform: UniformBuffer<CameraUniform>) {
+        self.uniform = Some(uniform);
+    }
+}
+
+/// Trauma based screen shake and zoom punch. Trauma accumulates with every
+/// hit and decays over time, shaking the view by the square of it so small
+/// hits barely move it. The punch briefly zooms in and eases back out.
+#[derive(Default)]
+pub struct CameraEffects {
+    trauma: f32,
+    punch: f32,
+    time: f32,
+}
+
+impl CameraEffects {
+    /// Adds trauma, in [0, 1]
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// Zooms in by the given fraction, easing back out
+    pub fn punch(&mut self, amount: f32) {
+        self.punch = self.punch.max(amount);
+    }
+
+    pub fn update(&mut self, elapsed_time: f32) {
+        self.time += elapsed_time;
+        self.trauma = (self.trauma - TRAUMA_DECAY * elapsed_time).max(0.0);
+        self.punch *= (-PUNCH_DECAY * elapsed_time).exp();
+    }
+
+    /// The effect transform to apply in view space
+    pub fn matrix(&self) -> Mat4 {
+        let shake = self.trauma * self.trauma;
+        let offset = vec2(noise(self.time, 0.0), noise(self.time, 1.0)) * MAX_SHAKE_OFFSET * shake;
+        let angle = noise(self.time, 2.0) * MAX_SHAKE_ANGLE * shake;
+        Mat4::from_translation(offset.extend(0.0))
+            * Mat4::from_rotation_z(angle)
+            * Mat4::from_scale(Vec3::new(1.0 + self.punch, 1.0 + self.punch, 1.0))
+    }
+}
+
+/// Smooth pseudo random noise in [-1, 1], a different curve for every seed
+fn noise(time: f32, seed: f32) -> f32 {
+    let phase = Vec2::splat(seed * 12.9898);
+    let [a, b] = (vec2(23.0, 37.0) * time + phase).to_array();
+    0.6 * a.sin() + 0.4 * b.sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec3, Vec4};
+
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-4), "{a} != {b}");
+    }
+
+    #[test]
+    fn ortho_projection() {
+        let camera = OrthoCamera::new(800, 600);
+        let proj = camera.proj_matrix();
+        // Column-major, w is left alone
+        assert_eq!(proj.row(3), Vec4::W);
+        assert_near(
+            proj.project_point3(vec3(400.0, 300.0, 0.0)),
+            vec3(1.0, 1.0, 0.5),
+        );
+        assert_near(
+            proj.project_point3(vec3(-400.0, -300.0, 0.0)),
+            vec3(-1.0, -1.0, 0.5),
+        );
+    }
+
+    #[test]
+    fn ortho_top_left_origin() {
+        let camera = OrthoCamera::with_origin(800, 600, Origin::TopLeft);
+        let view_proj = camera.view_proj_matrix();
+        assert_near(view_proj.project_point3(Vec3::ZERO), vec3(-1.0, 1.0, 0.5));
+        assert_near(
+            view_proj.project_point3(vec3(800.0, 600.0, 0.0)),
+            vec3(1.0, -1.0, 0.5),
+        );
+    }
+
+    #[test]
+    fn effects_applied_in_view_space() {
+        let camera = OrthoCamera::new(800, 600);
+        let shift = Mat4::from_translation(vec3(40.0, 0.0, 0.0));
+        let point = camera
+            .view_proj_matrix_with(shift)
+            .project_point3(Vec3::ZERO);
+        assert_near(point, vec3(0.1, 0.0, 0.5));
+    }
+
+    #[test]
+    fn perspective_projection() {
+        let camera = PerspectiveCamera::new(800, 600, vec2(800.0, 600.0), 1.0, 0.0);
+        let view_proj = camera.view_proj_matrix();
+        // Untilted, the playfield fills the screen
+        let corner = view_proj.project_point3(vec3(400.0, 300.0, 0.0));
+        assert_near(corner.truncate().extend(0.0), vec3(1.0, 1.0, 0.0));
+        assert!((0.0..=1.0).contains(&corner.z));
+    }
+
+    #[test]
+    fn letterbox_exact_fit() {
+        let letterbox = Letterbox::fit(1024, 768, vec2(1024.0, 768.0));
+        assert_eq!(
+            letterbox,
+            Letterbox {
+                x: 0,
+                y: 0,
+                width: 1024,
+                height: 768,
+                scale: 1.0,
+            }
+        );
+    }
+
+    #[test]
+    fn letterbox_wider_window() {
+        // Bars on the left and right
+        let letterbox = Letterbox::fit(1920, 768, vec2(1024.0, 768.0));
+        assert_eq!((letterbox.x, letterbox.y), (448, 0));
+        assert_eq!((letterbox.width, letterbox.height), (1024, 768));
+        assert_eq!(letterbox.scale, 1.0);
+    }
+
+    #[test]
+    fn letterbox_taller_window() {
+        // Bars above and below, scaled down to the width
+        let letterbox = Letterbox::fit(512, 1000, vec2(1024.0, 768.0));
+        assert_eq!((letterbox.x, letterbox.y), (0, 308));
+        assert_eq!((letterbox.width, letterbox.height), (512, 384));
+        assert_eq!(letterbox.scale, 0.5);
+    }
+
+    #[test]
+    fn letterbox_empty_window() {
+        let letterbox = Letterbox::fit(0, 0, vec2(1024.0, 768.0));
+        assert!(letterbox.width <= 1 && letterbox.height <= 1);
+        assert!(letterbox.scale > 0.0);
+    }
+
+    /// The inverse of Camera::window_to_world
+    fn world_to_window(camera: &impl Camera, world: Vec2) -> Vec2 {
+        let ndc = camera
+            .view_proj_matrix()
+            .project_point3(world.extend(0.0))
+            .truncate();
+        vec2(ndc.x + 1.0, 1.0 - ndc.y) / 2.0 * camera.size().as_vec2()
+    }
+
+    fn assert_round_trip(camera: &impl Camera) {
+        for world in [
+            Vec2::ZERO,
+            vec2(512.0, 384.0),
+            vec2(-512.0, 384.0),
+            vec2(100.0, -250.0),
+        ] {
+            let window = world_to_window(camera, world);
+            let back = camera.window_to_world(window).unwrap();
+            assert!(
+                back.abs_diff_eq(world, 1e-2),
+                "{world} -> {window} -> {back}"
+            );
+        }
+    }
+
+    #[test]
+    fn window_to_world_ortho() {
+        let camera = OrthoCamera::with_playfield(1920, 768, vec2(1024.0, 768.0));
+        // The window center is the world origin, with bars on the sides
+        let center = camera.window_to_world(vec2(960.0, 384.0)).unwrap();
+        assert!(center.abs_diff_eq(Vec2::ZERO, 1e-3));
+        let corner = camera.window_to_world(vec2(448.0, 0.0)).unwrap();
+        assert!(corner.abs_diff_eq(vec2(-512.0, 384.0), 1e-3));
+        assert_round_trip(&camera);
+    }
+
+    #[test]
+    fn window_to_world_perspective() {
+        let camera = PerspectiveCamera::new(
+            800,
+            600,
+            vec2(1024.0, 768.0),
+            45.0_f32.to_radians(),
+            30.0_f32.to_radians(),
+        );
+        assert_round_trip(&camera);
+    }
+
+    #[test]
+    fn trauma_is_clamped() {
+        let mut effects = CameraEffects::default();
+        effects.add_trauma(0.7);
+        effects.add_trauma(0.7);
+        assert_eq!(effects.trauma, 1.0);
+        effects.add_trauma(-3.0);
+        assert_eq!(effects.trauma, 0.0);
+    }
+
+    #[test]
+    fn effects_decay() {
+        let mut effects = CameraEffects::default();
+        effects.add_trauma(1.0);
+        effects.punch(0.2);
+        effects.update(0.1);
+        // Trauma decays linearly, the punch exponentially
+        assert!((effects.trauma - (1.0 - TRAUMA_DECAY * 0.1)).abs() < 1e-6);
+        assert!((effects.punch - 0.2 * (-PUNCH_DECAY * 0.1).exp()).abs() < 1e-6);
+        let punch = effects.punch;
+        effects.update(0.1);
+        assert!((effects.punch / punch - (-PUNCH_DECAY * 0.1).exp()).abs() < 1e-6);
+
+        // Trauma stops at exactly zero
+        effects.update(1.0 / TRAUMA_DECAY);
+        assert_eq!(effects.trauma, 0.0);
+    }
+
+    #[test]
+    fn effects_at_rest() {
+        let mut effects = CameraEffects::default();
+        assert_eq!(effects.matrix(), Mat4::IDENTITY);
+        // Time alone doesn't move the view
+        effects.update(1.5);
+        assert_eq!(effects.matrix(), Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn punch_scales() {
+        let mut effects = CameraEffects::default();
+        effects.punch(0.25);
+        let point = effects.matrix() * Vec4::new(2.0, -4.0, 3.0, 1.0);
+        assert_near(point.truncate(), vec3(2.5, -5.0, 3.0));
+
+        // A smaller punch doesn't cut a bigger one short
+        effects.punch(0.1);
+        assert_eq!(effects.punch, 0.25);
+    }
 }
@@ -0,0 +1,607 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Folder the captures are written to, relative to the working directory
+const CAPTURE_DIR: &str = "captures";
+/// Frames waiting on the GPU copy. Recording frames are skipped past this,
+/// rather than stalling the render loop.
+const MAX_PENDING: usize = 4;
+/// NeuQuant speed used to build the GIF palettes, 1 is the slowest and best
+const GIF_QUANTIZER_SPEED: i32 = 10;
+
+// Map states of a pending frame
+const MAP_PENDING: u8 = 0;
+const MAP_DONE: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+/// Format of a recording
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordingFormat {
+    /// A numbered PNG sequence in its own folder
+    PngSequence,
+    /// A single animated GIF
+    Gif,
+}
+
+/// Where a captured frame ends up
+#[derive(Debug, PartialEq)]
+enum Target {
+    Png(PathBuf),
+    Gif,
+}
+
+/// A frame copied into a staging buffer, waiting to be mapped
+struct PendingFrame {
+    buffer: wgpu::Buffer,
+    state: Arc<AtomicU8>,
+    mapping: bool,
+    target: Target,
+    captured_at: Instant,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+}
+
+impl PendingFrame {
+    /// Copies the mapped buffer out as is and unmaps it, the rows are converted
+    /// on the encoding thread
+    fn read(&self) -> RawFrame {
+        let bytes = self.buffer.slice(..).get_mapped_range().to_vec();
+        self.buffer.unmap();
+        RawFrame {
+            width: self.width,
+            height: self.height,
+            padded_bytes_per_row: self.padded_bytes_per_row,
+            bgra: self.bgra,
+            bytes,
+        }
+    }
+}
+
+/// A frame as copied from the surface, rows padded to the copy alignment
+struct RawFrame {
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+    bytes: Vec<u8>,
+}
+
+impl RawFrame {
+    fn into_rgba(self) -> Vec<u8> {
+        to_rgba(
+            &self.bytes,
+            self.width,
+            self.padded_bytes_per_row,
+            self.bgra,
+        )
+    }
+}
+
+/// Converts rows of padded_bytes_per_row bytes into tightly packed, opaque RGBA
+fn to_rgba(bytes: &[u8], width: u32, padded_bytes_per_row: u32, bgra: bool) -> Vec<u8> {
+    let bytes_per_row = (width * 4) as usize;
+    let mut pixels = Vec::with_capacity(bytes.len());
+    for row in bytes.chunks(padded_bytes_per_row as usize) {
+        pixels.extend_from_slice(&row[..bytes_per_row]);
+    }
+    // The surface is presented as opaque whatever its alpha, which would
+    // otherwise leave the captures transparent
+    for pixel in pixels.chunks_exact_mut(4) {
+        if bgra {
+            pixel.swap(0, 2);
+        }
+        pixel[3] = u8::MAX;
+    }
+    pixels
+}
+
+struct Recording {
+    format: RecordingFormat,
+    folder: PathBuf,
+    /// Frames rendered since the recording started
+    frame: u64,
+    /// Frames captured since the recording started
+    captured: u32,
+    /// Size of the first captured frame, GIF frames can't change it
+    size: Option<(u32, u32)>,
+}
+
+impl Recording {
+    /// Counts a rendered frame, returning whether it's one of the every Nth captured
+    fn count_frame(&mut self, every: u32) -> bool {
+        let frame = self.frame;
+        self.frame += 1;
+        frame.is_multiple_of(every as u64)
+    }
+
+    /// Where the next captured frame goes, PNG sequences are numbered from 0
+    fn next_target(&mut self) -> Target {
+        let target = match self.format {
+            RecordingFormat::PngSequence => {
+                Target::Png(self.folder.join(format!("frame_{:05}.png", self.captured)))
+            }
+            RecordingFormat::Gif => Target::Gif,
+        };
+        self.captured += 1;
+        target
+    }
+}
+
+/// Messages to the encoding thread
+enum Job {
+    Png(PathBuf, RawFrame),
+    StartGif(PathBuf),
+    GifFrame(RawFrame, Instant),
+    FinishGif,
+}
+
+/// Captures frames of the surface to disk. Frames are copied into staging
+/// buffers, mapped asynchronously and handed over to a worker thread for
+/// encoding, so capturing never waits on the GPU nor on the encoder.
+///
+/// Screenshots are timestamped PNGs. Recordings capture every Nth frame, either
+/// as a numbered PNG sequence or as an animated GIF.
+///
+/// Dropping it waits for the encoding thread to write what it was sent, and
+/// to finish a GIF still being recorded.
+pub struct Capture {
+    // Dropped first when the Capture is, ending the encoding thread
+    sender: Option<mpsc::Sender<Job>>,
+    thread: Option<thread::JoinHandle<()>>,
+    pending: Vec<PendingFrame>,
+    screenshot_requested: bool,
+    recording: Option<Recording>,
+    /// A GIF was stopped but some of its frames are still being copied
+    finish_gif: bool,
+    every: u32,
+}
+
+impl Capture {
+    /// Starts the encoding thread. Recordings capture one of every `every` frames.
+    pub fn new(every: u32) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("capture".into())
+            .spawn(move || encode(receiver))
+            .expect("Couldn't start the capture thread");
+        Self {
+            sender: Some(sender),
+            thread: Some(thread),
+            pending: Vec::new(),
+            screenshot_requested: false,
+            recording: None,
+            finish_gif: false,
+            every: every.max(1),
+        }
+    }
+
+    /// Captures the next frame as a timestamped PNG
+    pub fn request_screenshot(&mut self) {
+        self.screenshot_requested = true;
+    }
+
+    /// Starts a new recording, or stops the current one
+    pub fn toggle_recording(&mut self, format: RecordingFormat) {
+        if self.recording.is_some() {
+            self.stop_recording();
+            return;
+        }
+        if self.finish_gif {
+            log::warn!("Still finishing the previous GIF");
+            return;
+        }
+
+        let name = timestamped_name();
+        let folder = Path::new(CAPTURE_DIR).join(&name);
+        let created = match format {
+            RecordingFormat::PngSequence => std::fs::create_dir_all(&folder),
+            RecordingFormat::Gif => std::fs::create_dir_all(CAPTURE_DIR),
+        };
+        if let Err(error) = created {
+            log::error!("Couldn't start recording: {error}");
+            return;
+        }
+        if format == RecordingFormat::Gif {
+            self.send(Job::StartGif(folder.with_extension("gif")));
+        }
+        log::info!("Recording {format:?} to {}", folder.display());
+        self.recording = Some(Recording {
+            format,
+            folder,
+            frame: 0,
+            captured: 0,
+            size: None,
+        });
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            log::info!("Stopped recording, {} frames captured", recording.captured);
+            if recording.format == RecordingFormat::Gif {
+                self.finish_gif = true;
+                self.finish_gif_if_done();
+            }
+        }
+    }
+
+    /// Copies the surface texture into a staging buffer if this frame is to be captured.
+    /// The texture needs to have been created with COPY_SRC usage.
+    pub fn copy(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) {
+        let size = (texture.width(), texture.height());
+        let target = if std::mem::take(&mut self.screenshot_requested) {
+            let path = Path::new(CAPTURE_DIR).join(format!("{}.png", timestamped_name()));
+            log::info!("Saving screenshot to {}", path.display());
+            Target::Png(path)
+        } else if let Some(recording) = &mut self.recording {
+            if recording.format == RecordingFormat::Gif
+                && *recording.size.get_or_insert(size) != size
+            {
+                log::warn!("The window was resized, the GIF can't change its size");
+                self.stop_recording();
+                return;
+            }
+            if !recording.count_frame(self.every) {
+                return;
+            }
+            if self.pending.len() >= MAX_PENDING {
+                log::warn!("Skipping a recording frame, the GPU copies are falling behind");
+                return;
+            }
+            recording.next_target()
+        } else {
+            return;
+        };
+
+        let bgra = match texture.format() {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            format => {
+                log::error!("Can't capture frames in {format:?}");
+                return;
+            }
+        };
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            log::error!("Can't capture frames, the surface doesn't support copies");
+            return;
+        }
+
+        let (width, height) = size;
+        let padded_bytes_per_row = (width * 4).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("capture_buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        self.pending.push(PendingFrame {
+            buffer,
+            state: Arc::new(AtomicU8::new(MAP_PENDING)),
+            mapping: false,
+            target,
+            captured_at: Instant::now(),
+            width,
+            height,
+            padded_bytes_per_row,
+            bgra,
+        });
+    }
+
+    /// Maps the frames copied in the last submission and sends the ones already mapped
+    /// to the encoding thread. Should be called every frame after submitting.
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        self.send_mapped(device, wgpu::Maintain::Poll);
+    }
+
+    /// Stops recording and waits for the frames still being copied, so they're
+    /// sent to the encoding thread before the Capture is dropped
+    pub fn finish(&mut self, device: &wgpu::Device) {
+        self.stop_recording();
+        self.send_mapped(device, wgpu::Maintain::Wait);
+    }
+
+    fn send_mapped(&mut self, device: &wgpu::Device, maintain: wgpu::Maintain) {
+        if self.pending.is_empty() {
+            return;
+        }
+        for frame in self.pending.iter_mut().filter(|frame| !frame.mapping) {
+            let state = frame.state.clone();
+            frame
+                .buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    let mapped = if result.is_ok() { MAP_DONE } else { MAP_FAILED };
+                    state.store(mapped, Ordering::Release);
+                });
+            frame.mapping = true;
+        }
+        device.poll(maintain);
+
+        // Frames are sent in order, mapping is done by submission order
+        while self
+            .pending
+            .first()
+            .is_some_and(|frame| frame.state.load(Ordering::Acquire) != MAP_PENDING)
+        {
+            let frame = self.pending.remove(0);
+            if frame.state.load(Ordering::Acquire) == MAP_FAILED {
+                log::error!("Couldn't map a captured frame");
+                continue;
+            }
+            let raw = frame.read();
+            self.send(match frame.target {
+                Target::Png(path) => Job::Png(path, raw),
+                Target::Gif => Job::GifFrame(raw, frame.captured_at),
+            });
+        }
+        self.finish_gif_if_done();
+    }
+
+    /// Finishes a stopped GIF once all of its frames were sent
+    fn finish_gif_if_done(&mut self) {
+        let copying = self
+            .pending
+            .iter()
+            .any(|frame| matches!(frame.target, Target::Gif));
+        if self.finish_gif && !copying {
+            self.finish_gif = false;
+            self.send(Job::FinishGif);
+        }
+    }
+
+    fn send(&self, job: Job) {
+        let sent = self.sender.as_ref().map(|sender| sender.send(job));
+        if !matches!(sent, Some(Ok(()))) {
+            log::error!("The capture thread stopped, frame dropped");
+        }
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        if self
+            .recording
+            .as_ref()
+            .is_some_and(|recording| recording.format == RecordingFormat::Gif)
+            || self.finish_gif
+        {
+            self.send(Job::FinishGif);
+        }
+        // Closing the channel ends the thread once it encoded everything before
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                log::error!("The capture thread panicked");
+            }
+        }
+    }
+}
+
+/// Local date and time, used to name the captures
+fn timestamped_name() -> String {
+    format!(
+        "rusteroids_{}",
+        chrono::Local::now().format("%Y-%m-%d_%H-%M-%S%.3f")
+    )
+}
+
+/// GIF frames are held until the next one arrives, so their delay is the actual
+/// time between both captures
+struct GifRecording {
+    path: PathBuf,
+    encoder: Option<gif::Encoder<BufWriter<File>>>,
+    held: Option<(gif::Frame<'static>, Instant)>,
+    last_delay: u16,
+}
+
+impl GifRecording {
+    fn push(&mut self, raw: RawFrame, captured_at: Instant) {
+        let (width, height) = (raw.width, raw.height);
+        let mut pixels = raw.into_rgba();
+        if let Some((frame, previous)) = self.held.take() {
+            self.last_delay = hundredths(captured_at - previous);
+            self.write(frame, self.last_delay);
+        }
+        let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+            log::error!("Frame too large for a GIF: {width}x{height}");
+            return;
+        };
+        let frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, GIF_QUANTIZER_SPEED);
+        self.held = Some((frame, captured_at));
+    }
+
+    fn finish(mut self) {
+        if let Some((frame, _)) = self.held.take() {
+            self.write(frame, self.last_delay);
+        }
+        if self.encoder.is_some() {
+            log::info!("Saved {}", self.path.display());
+        }
+    }
+
+    fn write(&mut self, mut frame: gif::Frame<'static>, delay: u16) {
+        if self.encoder.is_none() {
+            let encoder = File::create(&self.path)
+                .map_err(gif::EncodingError::from)
+                .and_then(|file| {
+                    gif::Encoder::new(BufWriter::new(file), frame.width, frame.height, &[])
+                })
+                .and_then(|mut encoder| {
+                    encoder.set_repeat(gif::Repeat::Infinite)?;
+                    Ok(encoder)
+                });
+            match encoder {
+                Ok(encoder) => self.encoder = Some(encoder),
+                Err(error) => {
+                    log::error!("Couldn't create {}: {error}", self.path.display());
+                    return;
+                }
+            }
+        }
+        frame.delay = delay;
+        if let Some(Err(error)) = self
+            .encoder
+            .as_mut()
+            .map(|encoder| encoder.write_frame(&frame))
+        {
+            log::error!("Couldn't write to {}: {error}", self.path.display());
+        }
+    }
+}
+
+/// Converts a frame duration to the GIF delay unit
+fn hundredths(duration: Duration) -> u16 {
+    (duration.as_millis() / 10).clamp(1, u16::MAX as u128) as u16
+}
+
+/// Runs on the capture thread, encoding frames until the Capture is dropped
+fn encode(receiver: mpsc::Receiver<Job>) {
+    let mut gif: Option<GifRecording> = None;
+    for job in receiver {
+        match job {
+            Job::Png(path, raw) => {
+                match write_png(&path, raw.width, raw.height, &raw.into_rgba()) {
+                    Ok(()) => log::debug!("Saved {}", path.display()),
+                    Err(error) => log::error!("Couldn't write {}: {error}", path.display()),
+                }
+            }
+            Job::StartGif(path) => {
+                gif = Some(GifRecording {
+                    path,
+                    encoder: None,
+                    held: None,
+                    // Until there are two frames to measure, assume 60 fps
+                    last_delay: 2,
+                })
+            }
+            Job::GifFrame(raw, captured_at) => {
+                if let Some(gif) = &mut gif {
+                    gif.push(raw, captured_at);
+                }
+            }
+            Job::FinishGif => {
+                if let Some(gif) = gif.take() {
+                    gif.finish();
+                }
+            }
+        }
+    }
+    if let Some(gif) = gif {
+        gif.finish();
+    }
+}
+
+fn write_png(
+    path: &Path,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Result<(), png::EncodingError> {
+    // Screenshots can be the first capture, before any recording created the folder
+    if let Some(folder) = path.parent() {
+        std::fs::create_dir_all(folder)?;
+    }
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gif_delays() {
+        assert_eq!(hundredths(Duration::from_millis(33)), 3);
+        assert_eq!(hundredths(Duration::from_secs(1)), 100);
+        // GIF viewers treat a delay of 0 as "as fast as possible"
+        assert_eq!(hundredths(Duration::ZERO), 1);
+        assert_eq!(hundredths(Duration::from_millis(9)), 1);
+        assert_eq!(hundredths(Duration::from_secs(1000)), u16::MAX);
+    }
+
+    #[test]
+    fn unpads_rows() {
+        // Two rows of two pixels, each padded with 4 bytes
+        let bytes = [
+            1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0, //
+            9, 10, 11, 12, 13, 14, 15, 16, 0, 0, 0, 0,
+        ];
+        assert_eq!(
+            to_rgba(&bytes, 2, 12, false),
+            [1, 2, 3, 255, 5, 6, 7, 255, 9, 10, 11, 255, 13, 14, 15, 255]
+        );
+    }
+
+    #[test]
+    fn swizzles_bgra() {
+        let bytes = [10, 20, 30, 0, 40, 50, 60, 128];
+        assert_eq!(
+            to_rgba(&bytes, 2, 8, true),
+            [30, 20, 10, 255, 60, 50, 40, 255]
+        );
+    }
+
+    fn new_recording(format: RecordingFormat) -> Recording {
+        Recording {
+            format,
+            folder: PathBuf::from("captures/test"),
+            frame: 0,
+            captured: 0,
+            size: None,
+        }
+    }
+
+    #[test]
+    fn captures_every_nth_frame() {
+        let mut recording = new_recording(RecordingFormat::Gif);
+        let captured: Vec<bool> = (0..7).map(|_| recording.count_frame(3)).collect();
+        assert_eq!(captured, [true, false, false, true, false, false, true]);
+        let mut recording = new_recording(RecordingFormat::Gif);
+        assert!((0..4).all(|_| recording.count_frame(1)));
+    }
+
+    #[test]
+    fn numbers_png_sequences() {
+        let mut recording = new_recording(RecordingFormat::PngSequence);
+        assert_eq!(
+            recording.next_target(),
+            Target::Png(PathBuf::from("captures/test/frame_00000.png"))
+        );
+        assert_eq!(
+            recording.next_target(),
+            Target::Png(PathBuf::from("captures/test/frame_00001.png"))
+        );
+        assert_eq!(recording.captured, 2);
+
+        let mut recording = new_recording(RecordingFormat::Gif);
+        assert_eq!(recording.next_target(), Target::Gif);
+        assert_eq!(recording.captured, 1);
+    }
+}
@@ -0,0 +1,828 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::{Deref, Range};
+
+use wgpu_utils::reflect::{ReflectionError, ShaderReflection};
+
+/// A gadget to bind a Shader, VertexBuffers and Bindgroups into a RenderPipeline.
+/// Created through GadgetBuilder.
+pub struct Gadget {
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Deref for Gadget {
+    type Target = wgpu::RenderPipeline;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pipeline
+    }
+}
+
+/// Blending presets for the Gadget colour target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Blend {
+    /// Overwrites the target
+    Replace,
+    /// Regular alpha blending
+    Alpha,
+    /// Adds the colour weighted by its alpha, for glows and particles
+    Additive,
+    /// Keeps the brightest of both, so overlapping shapes don't blend twice
+    Max,
+    Custom(wgpu::BlendState),
+}
+
+impl Blend {
+    /// The blend state of the colour target, none when replacing so it works
+    /// with formats that aren't blendable
+    fn state(&self) -> Option<wgpu::BlendState> {
+        Some(match self {
+            Blend::Replace => return None,
+            Blend::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            Blend::Additive => {
+                let additive = wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                };
+                wgpu::BlendState {
+                    color: additive,
+                    alpha: additive,
+                }
+            }
+            Blend::Max => {
+                let max = wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Max,
+                };
+                wgpu::BlendState {
+                    color: max,
+                    alpha: max,
+                }
+            }
+            Blend::Custom(state) => *state,
+        })
+    }
+}
+
+/// Errors found while validating a Gadget, before handing it to wgpu
+#[derive(Clone, Debug, PartialEq)]
+pub enum GadgetError {
+    /// The shader has no entry point of that name for the stage
+    MissingEntryPoint {
+        name: String,
+        stage: wgpu::ShaderStages,
+    },
+    UnsupportedSampleCount(u32),
+    TooManyVertexBuffers {
+        count: usize,
+        max: u32,
+    },
+    TooManyVertexAttributes {
+        count: usize,
+        max: u32,
+    },
+    InvalidShaderLocation {
+        location: u32,
+        max: u32,
+    },
+    DuplicateShaderLocation(u32),
+    InvalidArrayStride {
+        buffer: usize,
+        stride: u64,
+    },
+    AttributeOutOfBounds {
+        buffer: usize,
+        location: u32,
+    },
+    TooManyBindGroups {
+        count: usize,
+        max: u32,
+    },
+    PushConstantsUnsupported,
+    PushConstantRangeTooLarge {
+        end: u32,
+        max: u32,
+    },
+    OverlappingPushConstantStages(wgpu::ShaderStages),
+    StripIndexFormatWithoutStrip(wgpu::PrimitiveTopology),
+    NotDepthStencilFormat(wgpu::TextureFormat),
+    /// The depth format needs features the device doesn't have
+    UnsupportedDepthFormat(wgpu::TextureFormat),
+    DepthSampleCount {
+        format: wgpu::TextureFormat,
+        count: u32,
+    },
+    NotBlendable(wgpu::TextureFormat),
+    InvalidMaxBlendFactors,
+    /// The layouts disagree with what the shader declares
+    Layout(ReflectionError),
+}
+
+impl fmt::Display for GadgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GadgetError::MissingEntryPoint { name, stage } => {
+                write!(f, "the shader has no {stage:?} entry point named {name}")
+            }
+            GadgetError::UnsupportedSampleCount(count) => {
+                write!(f, "sample count {count} isn't supported")
+            }
+            GadgetError::TooManyVertexBuffers { count, max } => {
+                write!(f, "{count} vertex buffers exceed the limit of {max}")
+            }
+            GadgetError::TooManyVertexAttributes { count, max } => {
+                write!(f, "{count} vertex attributes exceed the limit of {max}")
+            }
+            GadgetError::InvalidShaderLocation { location, max } => {
+                write!(f, "shader location {location} must be lower than {max}")
+            }
+            GadgetError::DuplicateShaderLocation(location) => {
+                write!(f, "shader location {location} is used more than once")
+            }
+            GadgetError::InvalidArrayStride { buffer, stride } => write!(
+                f,
+                "vertex buffer {buffer} has an invalid array stride of {stride}"
+            ),
+            GadgetError::AttributeOutOfBounds { buffer, location } => write!(
+                f,
+                "attribute at location {location} doesn't fit the stride of vertex buffer {buffer}"
+            ),
+            GadgetError::TooManyBindGroups { count, max } => {
+                write!(f, "{count} bind groups exceed the limit of {max}")
+            }
+            GadgetError::PushConstantsUnsupported => {
+                write!(f, "push constants require the PUSH_CONSTANTS feature")
+            }
+            GadgetError::PushConstantRangeTooLarge { end, max } => write!(
+                f,
+                "push constant range ending at {end} exceeds the limit of {max} bytes"
+            ),
+            GadgetError::OverlappingPushConstantStages(stages) => write!(
+                f,
+                "stages {stages:?} are in more than one push constant range"
+            ),
+            GadgetError::StripIndexFormatWithoutStrip(topology) => write!(
+                f,
+                "strip index format can only be used with strip topologies, not {topology:?}"
+            ),
+            GadgetError::NotDepthStencilFormat(format) => {
+                write!(f, "{format:?} isn't a depth or stencil format")
+            }
+            GadgetError::UnsupportedDepthFormat(format) => {
+                write!(f, "{format:?} requires features the device doesn't have")
+            }
+            GadgetError::DepthSampleCount { format, count } => {
+                write!(f, "{format:?} doesn't support a sample count of {count}")
+            }
+            GadgetError::NotBlendable(format) => write!(f, "{format:?} isn't blendable"),
+            GadgetError::InvalidMaxBlendFactors => {
+                write!(f, "min and max blend operations require One blend factors")
+            }
+            GadgetError::Layout(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for GadgetError {}
+
+impl From<ReflectionError> for GadgetError {
+    fn from(error: ReflectionError) -> Self {
+        GadgetError::Layout(error)
+    }
+}
+
+/// Builds a Gadget, validating its configuration against the device
+/// features and limits, and its entry points against the shader, instead of
+/// leaving wgpu to panic on it.
+///
+/// Defaults to the vs_main and fs_main entry points, a triangle list
+/// with no culling, replace blending, no depth and no multisampling.
+pub struct GadgetBuilder<'a> {
+    shader: &'a wgpu::ShaderModule,
+    config: GadgetConfig<'a>,
+}
+
+/// Everything a GadgetBuilder validates, kept apart from the shader so it can
+/// be checked without a device
+struct GadgetConfig<'a> {
+    format: wgpu::TextureFormat,
+    // The adapter's features for the format, the guaranteed ones when not given
+    format_features: Option<wgpu::TextureFormatFeatures>,
+    label: Option<&'a str>,
+    vs_entry_point: &'a str,
+    fs_entry_point: &'a str,
+    vertex_layouts: Vec<wgpu::VertexBufferLayout<'a>>,
+    bind_group_layouts: Vec<&'a wgpu::BindGroupLayout>,
+    push_constant_ranges: Vec<wgpu::PushConstantRange>,
+    primitive: wgpu::PrimitiveState,
+    blend: Blend,
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    sample_count: u32,
+}
+
+impl<'a> GadgetBuilder<'a> {
+    /// Starts a Gadget rendering with the shader into a colour target of the given format
+    pub fn new(shader: &'a wgpu::ShaderModule, format: wgpu::TextureFormat) -> Self {
+        Self {
+            shader,
+            config: GadgetConfig::new(format),
+        }
+    }
+
+    pub fn label(mut self, label: &'a str) -> Self {
+        self.config.label = Some(label);
+        self
+    }
+
+    /// The adapter's features for the colour format, needed for the sample counts
+    /// and blending it supports beyond the guaranteed ones
+    pub fn format_features(mut self, format_features: wgpu::TextureFormatFeatures) -> Self {
+        self.config.format_features = Some(format_features);
+        self
+    }
+
+    pub fn entry_points(mut self, vs_entry_point: &'a str, fs_entry_point: &'a str) -> Self {
+        self.config.vs_entry_point = vs_entry_point;
+        self.config.fs_entry_point = fs_entry_point;
+        self
+    }
+
+    /// Adds a vertex buffer layout, bound to the next vertex buffer slot
+    pub fn vertex_layout(mut self, layout: wgpu::VertexBufferLayout<'a>) -> Self {
+        self.config.vertex_layouts.push(layout);
+        self
+    }
+
+    /// Adds a bind group layout, bound to the next group index
+    pub fn bind_group_layout(mut self, layout: &'a wgpu::BindGroupLayout) -> Self {
+        self.config.bind_group_layouts.push(layout);
+        self
+    }
+
+    pub fn bind_group_layouts(mut self, layouts: &[&'a wgpu::BindGroupLayout]) -> Self {
+        self.config.bind_group_layouts.extend_from_slice(layouts);
+        self
+    }
+
+    /// Adds a push constant range, requires the PUSH_CONSTANTS feature
+    pub fn push_constants(mut self, stages: wgpu::ShaderStages, range: Range<u32>) -> Self {
+        self.config
+            .push_constant_ranges
+            .push(wgpu::PushConstantRange { stages, range });
+        self
+    }
+
+    pub fn topology(mut self, topology: wgpu::PrimitiveTopology) -> Self {
+        self.config.primitive.topology = topology;
+        self
+    }
+
+    pub fn strip_index_format(mut self, format: wgpu::IndexFormat) -> Self {
+        self.config.primitive.strip_index_format = Some(format);
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: Option<wgpu::Face>) -> Self {
+        self.config.primitive.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn blend(mut self, blend: Blend) -> Self {
+        self.config.blend = blend;
+        self
+    }
+
+    pub fn depth_stencil(mut self, depth_stencil: wgpu::DepthStencilState) -> Self {
+        self.config.depth_stencil = Some(depth_stencil);
+        self
+    }
+
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.config.sample_count = sample_count;
+        self
+    }
+
+    /// Validates the configuration and creates the RenderPipeline. The reflection
+    /// must be parsed from the source of the shader module.
+    pub fn build(
+        self,
+        device: &wgpu::Device,
+        reflection: &ShaderReflection,
+    ) -> Result<Gadget, GadgetError> {
+        let config = self.config;
+        config.validate(device.features(), &device.limits())?;
+        config.validate_entry_points(reflection)?;
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: config.label,
+            bind_group_layouts: &config.bind_group_layouts,
+            push_constant_ranges: &config.push_constant_ranges,
+        });
+
+        Ok(Gadget {
+            pipeline: device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: config.label,
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: self.shader,
+                    entry_point: config.vs_entry_point,
+                    buffers: &config.vertex_layouts,
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: self.shader,
+                    entry_point: config.fs_entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: config.format,
+                        blend: config.blend.state(),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: config.primitive,
+                depth_stencil: config.depth_stencil,
+                multisample: wgpu::MultisampleState {
+                    count: config.sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            }),
+        })
+    }
+}
+
+impl<'a> GadgetConfig<'a> {
+    fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            format,
+            format_features: None,
+            label: None,
+            vs_entry_point: "vs_main",
+            fs_entry_point: "fs_main",
+            vertex_layouts: Vec::new(),
+            bind_group_layouts: Vec::new(),
+            push_constant_ranges: Vec::new(),
+            primitive: wgpu::PrimitiveState::default(),
+            blend: Blend::Replace,
+            depth_stencil: None,
+            sample_count: 1,
+        }
+    }
+
+    fn validate(&self, features: wgpu::Features, limits: &wgpu::Limits) -> Result<(), GadgetError> {
+        // The adapter's format features can only be relied on when the device enabled them
+        let format_features = match self.format_features {
+            Some(format_features)
+                if features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) =>
+            {
+                format_features
+            }
+            _ => self.format.guaranteed_format_features(features),
+        };
+        if !format_features
+            .flags
+            .sample_count_supported(self.sample_count)
+        {
+            return Err(GadgetError::UnsupportedSampleCount(self.sample_count));
+        }
+
+        self.validate_vertex_layouts(limits)?;
+
+        if self.bind_group_layouts.len() > limits.max_bind_groups as usize {
+            return Err(GadgetError::TooManyBindGroups {
+                count: self.bind_group_layouts.len(),
+                max: limits.max_bind_groups,
+            });
+        }
+
+        if !self.push_constant_ranges.is_empty() {
+            if !features.contains(wgpu::Features::PUSH_CONSTANTS) {
+                return Err(GadgetError::PushConstantsUnsupported);
+            }
+            let mut stages = wgpu::ShaderStages::NONE;
+            for range in &self.push_constant_ranges {
+                if range.range.end > limits.max_push_constant_size {
+                    return Err(GadgetError::PushConstantRangeTooLarge {
+                        end: range.range.end,
+                        max: limits.max_push_constant_size,
+                    });
+                }
+                if stages.intersects(range.stages) {
+                    return Err(GadgetError::OverlappingPushConstantStages(
+                        stages & range.stages,
+                    ));
+                }
+                stages |= range.stages;
+            }
+        }
+
+        if self.primitive.strip_index_format.is_some() && !self.primitive.topology.is_strip() {
+            return Err(GadgetError::StripIndexFormatWithoutStrip(
+                self.primitive.topology,
+            ));
+        }
+
+        if let Some(depth_stencil) = &self.depth_stencil {
+            let format = depth_stencil.format;
+            if !format.is_depth_stencil_format() {
+                return Err(GadgetError::NotDepthStencilFormat(format));
+            }
+            if !features.contains(format.required_features()) {
+                return Err(GadgetError::UnsupportedDepthFormat(format));
+            }
+            // The depth target is multisampled like the colour target
+            if !format
+                .guaranteed_format_features(features)
+                .flags
+                .sample_count_supported(self.sample_count)
+            {
+                return Err(GadgetError::DepthSampleCount {
+                    format,
+                    count: self.sample_count,
+                });
+            }
+        }
+
+        let Some(blend) = self.blend.state() else {
+            return Ok(());
+        };
+        if !format_features
+            .flags
+            .contains(wgpu::TextureFormatFeatureFlags::BLENDABLE)
+        {
+            return Err(GadgetError::NotBlendable(self.format));
+        }
+        for component in [blend.color, blend.alpha] {
+            let min_max = matches!(
+                component.operation,
+                wgpu::BlendOperation::Min | wgpu::BlendOperation::Max
+            );
+            if min_max
+                && (component.src_factor != wgpu::BlendFactor::One
+                    || component.dst_factor != wgpu::BlendFactor::One)
+            {
+                return Err(GadgetError::InvalidMaxBlendFactors);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_entry_points(&self, reflection: &ShaderReflection) -> Result<(), GadgetError> {
+        for (name, stage) in [
+            (self.vs_entry_point, wgpu::ShaderStages::VERTEX),
+            (self.fs_entry_point, wgpu::ShaderStages::FRAGMENT),
+        ] {
+            if reflection.entry_point_stage(name) != Some(stage) {
+                return Err(GadgetError::MissingEntryPoint {
+                    name: name.to_string(),
+                    stage,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_vertex_layouts(&self, limits: &wgpu::Limits) -> Result<(), GadgetError> {
+        if self.vertex_layouts.len() > limits.max_vertex_buffers as usize {
+            return Err(GadgetError::TooManyVertexBuffers {
+                count: self.vertex_layouts.len(),
+                max: limits.max_vertex_buffers,
+            });
+        }
+        let attribute_count = self
+            .vertex_layouts
+            .iter()
+            .map(|layout| layout.attributes.len())
+            .sum();
+        if attribute_count > limits.max_vertex_attributes as usize {
+            return Err(GadgetError::TooManyVertexAttributes {
+                count: attribute_count,
+                max: limits.max_vertex_attributes,
+            });
+        }
+
+        let mut locations = HashSet::new();
+        for (buffer, layout) in self.vertex_layouts.iter().enumerate() {
+            if layout.array_stride % wgpu::VERTEX_STRIDE_ALIGNMENT != 0
+                || layout.array_stride > limits.max_vertex_buffer_array_stride as u64
+            {
+                return Err(GadgetError::InvalidArrayStride {
+                    buffer,
+                    stride: layout.array_stride,
+                });
+            }
+            for attribute in layout.attributes {
+                let location = attribute.shader_location;
+                if location >= limits.max_vertex_attributes {
+                    return Err(GadgetError::InvalidShaderLocation {
+                        location,
+                        max: limits.max_vertex_attributes,
+                    });
+                }
+                if !locations.insert(location) {
+                    return Err(GadgetError::DuplicateShaderLocation(location));
+                }
+                // A stride of zero means the attributes are read once for every vertex
+                if layout.array_stride != 0
+                    && attribute.offset + attribute.format.size() > layout.array_stride
+                {
+                    return Err(GadgetError::AttributeOutOfBounds { buffer, location });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x3];
+
+    fn validate(config: &GadgetConfig) -> Result<(), GadgetError> {
+        config.validate(wgpu::Features::empty(), &wgpu::Limits::default())
+    }
+
+    fn vertex_layout(
+        array_stride: u64,
+        attributes: &[wgpu::VertexAttribute],
+    ) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes,
+        }
+    }
+
+    fn adapter_features(flags: wgpu::TextureFormatFeatureFlags) -> wgpu::TextureFormatFeatures {
+        wgpu::TextureFormatFeatures {
+            allowed_usages: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            flags,
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        let mut config = GadgetConfig::new(FORMAT);
+        config.vertex_layouts.push(vertex_layout(20, &ATTRIBUTES));
+        assert_eq!(validate(&config), Ok(()));
+    }
+
+    #[test]
+    fn entry_points() {
+        let reflection = ShaderReflection::parse(
+            "@vertex fn vs_main() -> @builtin(position) vec4<f32> { return vec4<f32>(); }\n\
+             @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(); }",
+        )
+        .unwrap();
+        let mut config = GadgetConfig::new(FORMAT);
+        assert_eq!(config.validate_entry_points(&reflection), Ok(()));
+
+        config.fs_entry_point = "fs_glow";
+        assert_eq!(
+            config.validate_entry_points(&reflection),
+            Err(GadgetError::MissingEntryPoint {
+                name: "fs_glow".to_string(),
+                stage: wgpu::ShaderStages::FRAGMENT
+            })
+        );
+        // Entry points of the wrong stage are missing too
+        config.fs_entry_point = "vs_main";
+        assert_eq!(
+            config.validate_entry_points(&reflection),
+            Err(GadgetError::MissingEntryPoint {
+                name: "vs_main".to_string(),
+                stage: wgpu::ShaderStages::FRAGMENT
+            })
+        );
+    }
+
+    #[test]
+    fn sample_counts() {
+        let mut config = GadgetConfig::new(FORMAT);
+        config.sample_count = 4;
+        assert_eq!(validate(&config), Ok(()));
+        config.sample_count = 3;
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::UnsupportedSampleCount(3))
+        );
+
+        // Adapter specific counts need the device feature and the adapter's support
+        config.sample_count = 16;
+        config.format_features = Some(adapter_features(
+            wgpu::TextureFormatFeatureFlags::MULTISAMPLE_X16,
+        ));
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::UnsupportedSampleCount(16))
+        );
+        let adapter_specific = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+        assert_eq!(
+            config.validate(adapter_specific, &wgpu::Limits::default()),
+            Ok(())
+        );
+        config.format_features = Some(adapter_features(
+            wgpu::TextureFormatFeatureFlags::MULTISAMPLE_X2
+                | wgpu::TextureFormatFeatureFlags::MULTISAMPLE_X8,
+        ));
+        assert_eq!(
+            config.validate(adapter_specific, &wgpu::Limits::default()),
+            Err(GadgetError::UnsupportedSampleCount(16))
+        );
+    }
+
+    #[test]
+    fn vertex_layouts() {
+        let mut config = GadgetConfig::new(FORMAT);
+        config.vertex_layouts.push(vertex_layout(20, &ATTRIBUTES));
+        config.vertex_layouts.push(vertex_layout(20, &ATTRIBUTES));
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::DuplicateShaderLocation(0))
+        );
+
+        let mut config = GadgetConfig::new(FORMAT);
+        config.vertex_layouts.push(vertex_layout(16, &ATTRIBUTES));
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::AttributeOutOfBounds {
+                buffer: 0,
+                location: 1
+            })
+        );
+
+        let mut config = GadgetConfig::new(FORMAT);
+        config.vertex_layouts.push(vertex_layout(22, &ATTRIBUTES));
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::InvalidArrayStride {
+                buffer: 0,
+                stride: 22
+            })
+        );
+
+        let far = wgpu::vertex_attr_array![40 => Float32];
+        let mut config = GadgetConfig::new(FORMAT);
+        config.vertex_layouts.push(vertex_layout(4, &far));
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::InvalidShaderLocation {
+                location: 40,
+                max: 16
+            })
+        );
+
+        let mut config = GadgetConfig::new(FORMAT);
+        config.vertex_layouts = vec![vertex_layout(0, &[]); 9];
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::TooManyVertexBuffers { count: 9, max: 8 })
+        );
+    }
+
+    #[test]
+    fn push_constants() {
+        let range = |stages, range| wgpu::PushConstantRange { stages, range };
+        let mut config = GadgetConfig::new(FORMAT);
+        config
+            .push_constant_ranges
+            .push(range(wgpu::ShaderStages::VERTEX, 0..64));
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::PushConstantsUnsupported)
+        );
+        // The default limits allow no push constants, the missing feature is the cause
+        config.push_constant_ranges[0] = range(wgpu::ShaderStages::VERTEX, 0..256);
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::PushConstantsUnsupported)
+        );
+        config.push_constant_ranges[0] = range(wgpu::ShaderStages::VERTEX, 0..64);
+
+        let limits = wgpu::Limits {
+            max_push_constant_size: 128,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(wgpu::Features::PUSH_CONSTANTS, &limits),
+            Ok(())
+        );
+        config
+            .push_constant_ranges
+            .push(range(wgpu::ShaderStages::VERTEX_FRAGMENT, 64..128));
+        assert_eq!(
+            config.validate(wgpu::Features::PUSH_CONSTANTS, &limits),
+            Err(GadgetError::OverlappingPushConstantStages(
+                wgpu::ShaderStages::VERTEX
+            ))
+        );
+        config.push_constant_ranges[1] = range(wgpu::ShaderStages::FRAGMENT, 64..256);
+        assert_eq!(
+            config.validate(wgpu::Features::PUSH_CONSTANTS, &limits),
+            Err(GadgetError::PushConstantRangeTooLarge { end: 256, max: 128 })
+        );
+    }
+
+    #[test]
+    fn primitive_and_depth() {
+        let mut config = GadgetConfig::new(FORMAT);
+        config.primitive.strip_index_format = Some(wgpu::IndexFormat::Uint16);
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::StripIndexFormatWithoutStrip(
+                wgpu::PrimitiveTopology::TriangleList
+            ))
+        );
+        config.primitive.topology = wgpu::PrimitiveTopology::LineStrip;
+        assert_eq!(validate(&config), Ok(()));
+
+        config.depth_stencil = Some(wgpu::DepthStencilState {
+            format: FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: Default::default(),
+            bias: Default::default(),
+        });
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::NotDepthStencilFormat(FORMAT))
+        );
+
+        let depth = |format| wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: Default::default(),
+            bias: Default::default(),
+        };
+        let mut config = GadgetConfig::new(FORMAT);
+        config.depth_stencil = Some(depth(wgpu::TextureFormat::Depth24Plus));
+        config.sample_count = 4;
+        assert_eq!(validate(&config), Ok(()));
+
+        let format = wgpu::TextureFormat::Depth32FloatStencil8;
+        config.depth_stencil = Some(depth(format));
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::UnsupportedDepthFormat(format))
+        );
+        assert_eq!(
+            config.validate(
+                wgpu::Features::DEPTH32FLOAT_STENCIL8,
+                &wgpu::Limits::default()
+            ),
+            Ok(())
+        );
+
+        // The colour target supports more samples than the depth format guarantees
+        let adapter_specific = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+        config.depth_stencil = Some(depth(wgpu::TextureFormat::Depth24Plus));
+        config.sample_count = 8;
+        config.format_features = Some(adapter_features(
+            wgpu::TextureFormatFeatureFlags::MULTISAMPLE_X8,
+        ));
+        assert_eq!(
+            config.validate(adapter_specific, &wgpu::Limits::default()),
+            Err(GadgetError::DepthSampleCount {
+                format: wgpu::TextureFormat::Depth24Plus,
+                count: 8
+            })
+        );
+    }
+
+    #[test]
+    fn blending() {
+        // Replacing doesn't blend, so it works with formats that aren't blendable
+        let mut config = GadgetConfig::new(wgpu::TextureFormat::Rgba32Float);
+        assert_eq!(Blend::Replace.state(), None);
+        assert_eq!(validate(&config), Ok(()));
+        config.blend = Blend::Additive;
+        assert_eq!(
+            validate(&config),
+            Err(GadgetError::NotBlendable(wgpu::TextureFormat::Rgba32Float))
+        );
+
+        let mut config = GadgetConfig::new(FORMAT);
+        config.blend = Blend::Alpha;
+        assert_eq!(validate(&config), Ok(()));
+        let max = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Max,
+        };
+        config.blend = Blend::Custom(wgpu::BlendState {
+            color: max,
+            alpha: max,
+        });
+        assert_eq!(validate(&config), Err(GadgetError::InvalidMaxBlendFactors));
+    }
+}
@@ -1,7 +1,10 @@
-use glam::IVec2;
-use logic::Rusteroids;
-use mesh::Geometry;
+use camera::{Camera, OrthoCamera, PerspectiveCamera};
+use glam::vec2;
+use logic::{Rusteroids, PLAYFIELD};
+use mesh::{Geometry, Primitive};
 use renderer::Renderer;
+use settings::RenderSettings;
+use text::{TextAlign, TextStyle};
 use winit::{
     event::*,
     event_loop::EventLoop,
@@ -11,10 +14,22 @@ use winit::{
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
+mod asteroids;
+mod buffers;
 mod camera;
+#[cfg(not(target_arch = "wasm32"))]
+mod capture;
+pub mod gadget;
+mod lines;
 mod logic;
 mod mesh;
+mod particles;
+mod phosphor;
 mod renderer;
+mod settings;
+mod shaders;
+mod svg;
+mod text;
 mod utils;
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
@@ -53,15 +68,39 @@ pub async fn run() {
     // Game logic
     let mut game_logic = Rusteroids::new();
     let (mut is_w_pressed, mut is_a_pressed, mut is_d_pressed) = (false, false, false);
+    // Last mouse or touch position in window pixels
+    let mut pointer = glam::Vec2::ZERO;
+    let mut is_pointer_pressed = false;
+    // Pseudo 3D mode, tilting the playfield
+    let mut is_tilted = false;
     // Create the Renderer
-    let mut renderer = Renderer::new(&window).await;
+    let mut renderer = Renderer::new(
+        &window,
+        world_camera(is_tilted, window.inner_size()),
+        RenderSettings::default(),
+    )
+    .await;
     // Create Meshes
-    let mut meshes = vec![Geometry::new(
-        utils::WEDGE,
+    let mut meshes = vec![Geometry::with_indices(
+        &game_logic.ship_mesh().vertices,
+        &game_logic.ship_mesh().indices,
+        Primitive::LineList,
         renderer.get_device(),
         renderer.get_uniform_binding("model"),
         0,
     )];
+    // Asteroids follow the spaceship, the HUD uses mesh indices 1 and 2
+    meshes.extend(game_logic.asteroid_shapes().enumerate().map(|(i, shape)| {
+        Geometry::with_indices(
+            &shape.vertices,
+            &shape.indices,
+            Primitive::LineList,
+            renderer.get_device(),
+            renderer.get_uniform_binding("model"),
+            3 + i as u32,
+        )
+    }));
+    let mut hud = Vec::new();
     let mut surface_configured = false;
 
     // TODO: this event loop is too noisy, needs simplification
@@ -71,75 +110,121 @@ pub async fn run() {
                 Event::WindowEvent {
                     ref event,
                     window_id,
-                } if window_id == renderer.window().id() => {
-                    if !renderer.input(event) {
-                        match event {
-                            WindowEvent::CloseRequested
-                            | WindowEvent::KeyboardInput {
-                                event:
-                                    KeyEvent {
-                                        state: ElementState::Pressed,
-                                        physical_key: PhysicalKey::Code(KeyCode::Escape),
-                                        ..
-                                    },
-                                ..
-                            } => control_flow.exit(),
-                            WindowEvent::KeyboardInput { event, .. } => {
-                                match event.physical_key {
-                                    PhysicalKey::Code(KeyCode::KeyW) => {
-                                        is_w_pressed = matches!(event.state, ElementState::Pressed)
-                                    }
-                                    PhysicalKey::Code(KeyCode::KeyA) => {
-                                        is_a_pressed = matches!(event.state, ElementState::Pressed)
-                                    }
-                                    PhysicalKey::Code(KeyCode::KeyD) => {
-                                        is_d_pressed = matches!(event.state, ElementState::Pressed)
-                                    }
-                                    _ => {}
-                                };
-                                game_logic.update_keys(is_w_pressed, is_a_pressed, is_d_pressed);
+                } if window_id == renderer.window().id() && !renderer.input(event) => {
+                    match event {
+                        WindowEvent::CloseRequested
+                        | WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    state: ElementState::Pressed,
+                                    physical_key: PhysicalKey::Code(KeyCode::Escape),
+                                    ..
+                                },
+                            ..
+                        } => control_flow.exit(),
+                        WindowEvent::KeyboardInput { event, .. } => {
+                            match event.physical_key {
+                                PhysicalKey::Code(KeyCode::KeyW) => {
+                                    is_w_pressed = matches!(event.state, ElementState::Pressed)
+                                }
+                                PhysicalKey::Code(KeyCode::KeyA) => {
+                                    is_a_pressed = matches!(event.state, ElementState::Pressed)
+                                }
+                                PhysicalKey::Code(KeyCode::KeyD) => {
+                                    is_d_pressed = matches!(event.state, ElementState::Pressed)
+                                }
+                                PhysicalKey::Code(KeyCode::KeyT)
+                                    if event.state == ElementState::Pressed && !event.repeat =>
+                                {
+                                    is_tilted = !is_tilted;
+                                    renderer
+                                        .set_camera(world_camera(is_tilted, renderer.get_size()));
+                                }
+                                _ => {}
+                            };
+                            game_logic.update_keys(is_w_pressed, is_a_pressed, is_d_pressed);
+                        }
+                        WindowEvent::CursorMoved { .. }
+                        | WindowEvent::MouseInput { .. }
+                        | WindowEvent::Touch(_) => {
+                            match event {
+                                WindowEvent::CursorMoved { position, .. } => {
+                                    pointer = vec2(position.x as f32, position.y as f32)
+                                }
+                                WindowEvent::MouseInput {
+                                    state,
+                                    button: MouseButton::Left,
+                                    ..
+                                } => is_pointer_pressed = *state == ElementState::Pressed,
+                                WindowEvent::Touch(touch) => {
+                                    pointer =
+                                        vec2(touch.location.x as f32, touch.location.y as f32);
+                                    is_pointer_pressed = matches!(
+                                        touch.phase,
+                                        TouchPhase::Started | TouchPhase::Moved
+                                    );
+                                }
+                                _ => {}
                             }
-                            WindowEvent::Resized(physical_size) => {
-                                log::info!("physical_size: {physical_size:?}");
-                                surface_configured = true;
-                                game_logic.set_bounds(IVec2::new(
-                                    physical_size.width as i32,
-                                    physical_size.height as i32,
-                                ));
-                                renderer.resize(*physical_size);
+                            // The spaceship follows the pointer while it's pressed, the keys otherwise
+                            if is_pointer_pressed {
+                                game_logic.update_pointer(renderer.window_to_world(pointer));
+                            } else {
+                                game_logic.update_pointer(None);
+                                game_logic.update_keys(is_w_pressed, is_a_pressed, is_d_pressed);
                             }
-                            WindowEvent::RedrawRequested => {
-                                renderer.window().request_redraw();
+                        }
+                        WindowEvent::Resized(physical_size) => {
+                            log::info!("physical_size: {physical_size:?}");
+                            surface_configured = true;
+                            renderer.resize(*physical_size);
+                            layout_hud(&renderer, &mut hud);
+                        }
+                        WindowEvent::RedrawRequested => {
+                            renderer.window().request_redraw();
 
-                                if !surface_configured {
-                                    return;
+                            if !surface_configured {
+                                return;
+                            }
+                            game_logic.tick();
+                            renderer.set_camera_effect(game_logic.get_camera_effect_matrix());
+                            meshes.get_mut(0).unwrap().update_buffer(
+                                renderer.get_queue(),
+                                &game_logic.get_battleship_model_matrix(),
+                            );
+                            for (mesh, model_matrix) in meshes[1..]
+                                .iter_mut()
+                                .zip(game_logic.get_asteroid_model_matrices())
+                            {
+                                mesh.update_buffer(renderer.get_queue(), &model_matrix);
+                            }
+                            if let Some(flame) = game_logic.get_flame_vertices() {
+                                renderer.stream(&flame, std::iter::once(0..flame.len() as u32));
+                            }
+                            // The spaceship is hidden until it respawns
+                            let world = if game_logic.is_player_alive() {
+                                &meshes[..]
+                            } else {
+                                &meshes[1..]
+                            };
+                            match renderer.render(world, game_logic.particles(), &hud) {
+                                Ok(_) => {}
+                                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                                    let size = renderer.get_size();
+                                    renderer.resize(size)
+                                }
+                                Err(wgpu::SurfaceError::OutOfMemory) => {
+                                    log::error!("OutOfMemory");
+                                    control_flow.exit();
                                 }
-                                game_logic.tick();
-                                meshes.get_mut(0).unwrap().update_buffer(
-                                    renderer.get_queue(),
-                                    &game_logic.get_battleship_model_matrix(),
-                                );
-                                match renderer.render(&meshes) {
-                                    Ok(_) => {}
-                                    Err(
-                                        wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated,
-                                    ) => {
-                                        let size = renderer.get_size();
-                                        renderer.resize(size)
-                                    }
-                                    Err(wgpu::SurfaceError::OutOfMemory) => {
-                                        log::error!("OutOfMemory");
-                                        control_flow.exit();
-                                    }
 
-                                    // This happens when the a frame takes too long to present
-                                    Err(wgpu::SurfaceError::Timeout) => {
-                                        log::warn!("Surface timeout")
-                                    }
+                                // This happens when the a frame takes too long to present
+                                Err(wgpu::SurfaceError::Timeout) => {
+                                    log::warn!("Surface timeout")
                                 }
                             }
-                            _ => {}
                         }
+                        _ => {}
                     }
                 }
                 _ => {}
@@ -147,3 +232,79 @@ pub async fn run() {
         })
         .unwrap();
 }
+
+/// The camera looking at the playfield, tilted in pseudo 3D mode
+fn world_camera(is_tilted: bool, size: winit::dpi::PhysicalSize<u32>) -> Box<dyn Camera> {
+    if is_tilted {
+        Box::new(PerspectiveCamera::new(
+            size.width,
+            size.height,
+            PLAYFIELD,
+            45.0_f32.to_radians(),
+            30.0_f32.to_radians(),
+        ))
+    } else {
+        Box::new(OrthoCamera::with_playfield(
+            size.width,
+            size.height,
+            PLAYFIELD,
+        ))
+    }
+}
+
+/// Lays out the HUD text for the current window size, creating its Geometries
+/// the first time and updating them afterwards
+fn layout_hud<C: Camera>(renderer: &Renderer<C>, hud: &mut Vec<Geometry>) {
+    let size = renderer.get_size();
+    let scale_factor = renderer.window().scale_factor() as f32;
+    let title = TextStyle {
+        align: TextAlign::Center,
+        scale: 4.0 * scale_factor,
+        ..Default::default()
+    };
+    let help = TextStyle {
+        align: TextAlign::Right,
+        scale: 1.5 * scale_factor,
+        color: [0.6, 0.6, 0.6],
+    };
+    let margin = 16.0 * scale_factor;
+    let texts = [
+        ("RUSTEROIDS", vec2(size.width as f32 / 2.0, margin), title),
+        (
+            "P PHOSPHOR  M MSAA  [ ] LINE WIDTH  T TILT  F1 REDUCED MOTION",
+            vec2(
+                size.width as f32 - margin,
+                size.height as f32 - margin - 6.0 * help.scale,
+            ),
+            help,
+        ),
+    ];
+    if hud.is_empty() {
+        // The HUD uses mesh indices 1 and 2
+        *hud = texts
+            .iter()
+            .zip(1..)
+            .map(|((text, position, style), mesh_index)| {
+                text::geometry(
+                    text,
+                    *position,
+                    style,
+                    renderer.get_device(),
+                    renderer.get_uniform_binding("model"),
+                    mesh_index,
+                )
+            })
+            .collect();
+    } else {
+        for (geometry, (text, position, style)) in hud.iter_mut().zip(&texts) {
+            text::update_geometry(
+                geometry,
+                text,
+                *position,
+                style,
+                renderer.get_device(),
+                renderer.get_queue(),
+            );
+        }
+    }
+}
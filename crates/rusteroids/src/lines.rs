@@ -0,0 +1,124 @@
+use wgpu::RenderPass;
+use wgpu_utils::{Bindable, UniformLayout, WgslStruct};
+
+use crate::gadget::{Blend, Gadget, GadgetBuilder, GadgetError};
+use crate::mesh::Vertex;
+use crate::shaders::Shader;
+use crate::utils::{UniformBinding, UniformBindings, UniformBuffer};
+
+/// Default line width in logical pixels
+pub const DEFAULT_LINE_WIDTH: f32 = 2.0;
+
+/// Layout of the line uniform, bound to group 2 of the line pipeline
+pub const LINE_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+    label: Some("line_layout"),
+    entries: UniformBindings::ENTRIES,
+};
+
+/// Both ends of a segment are read from the same vertex buffer, the end one
+/// bound with an offset of one vertex, so each instance draws the segment
+/// between two consecutive vertices of a line strip.
+const SEGMENT_START_ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+    wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x3];
+const SEGMENT_END_ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+    wgpu::vertex_attr_array![2 => Float32x2, 3 => Float32x3];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, UniformLayout, WgslStruct)]
+pub struct LineUniform {
+    /// Viewport size in pixels
+    viewport: [f32; 2],
+    /// Half of the line width in pixels
+    half_width: f32,
+    _padding: f32,
+}
+
+/// Renders line strips as thick anti-aliased lines, expanding each segment
+/// into a screen space quad in the vertex shader. Joins and caps are rounded,
+/// and edges are anti-aliased analytically in the fragment shader.
+pub struct LineRenderer {
+    gadget: Gadget,
+    uniform: UniformBuffer<LineUniform>,
+}
+
+impl LineRenderer {
+    /// Creates the line pipeline. Camera and model bindings are expected to
+    /// match the ones used by the default Gadget.
+    pub fn new(
+        device: &wgpu::Device,
+        shader: &Shader,
+        camera_layout: &wgpu::BindGroupLayout,
+        model_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        format_features: wgpu::TextureFormatFeatures,
+        sample_count: u32,
+    ) -> Result<Self, GadgetError> {
+        let [start_layout, end_layout] = segment_layouts();
+        let line_binding = UniformBinding::new(device, LINE_LAYOUT);
+        let uniform = UniformBuffer::new(
+            &LineUniform {
+                viewport: [1.0, 1.0],
+                half_width: DEFAULT_LINE_WIDTH / 2.0,
+                _padding: 0.0,
+            },
+            device,
+            &line_binding,
+            "line",
+        );
+
+        // Overlapping segments at joins would otherwise blend twice
+        let gadget = GadgetBuilder::new(&shader.module(device), format)
+            .label("Line Pipeline")
+            .format_features(format_features)
+            .vertex_layout(start_layout)
+            .vertex_layout(end_layout)
+            .bind_group_layouts(&[camera_layout, model_layout, &line_binding])
+            .blend(Blend::Max)
+            .sample_count(sample_count)
+            .build(device, &shader.reflect()?)?;
+
+        Ok(Self { gadget, uniform })
+    }
+
+    /// Updates the viewport and the width, given in logical pixels and scaled
+    /// by the window scale factor
+    pub fn update_buffer(
+        &self,
+        queue: &wgpu::Queue,
+        size: winit::dpi::PhysicalSize<u32>,
+        width: f32,
+        scale_factor: f64,
+    ) {
+        self.uniform.update_buffer(
+            &LineUniform {
+                viewport: [size.width as f32, size.height as f32],
+                half_width: width * scale_factor as f32 / 2.0,
+                _padding: 0.0,
+            },
+            queue,
+        );
+    }
+
+    /// Sets the line pipeline and its uniform. Camera and model still need to be bound.
+    pub fn bind(&self, pass: &mut RenderPass<'_>) {
+        pass.set_pipeline(&self.gadget);
+        self.uniform.bind(pass, 2);
+    }
+}
+
+/// Vertex buffer layouts for the start and end of each segment, stepping per instance
+pub fn segment_layouts() -> [wgpu::VertexBufferLayout<'static>; 2] {
+    let stride = std::mem::size_of::<Vertex>() as wgpu::BufferAddress;
+    [
+        wgpu::VertexBufferLayout {
+            array_stride: stride,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &SEGMENT_START_ATTRIBUTES,
+        },
+        wgpu::VertexBufferLayout {
+            array_stride: stride,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &SEGMENT_END_ATTRIBUTES,
+        },
+    ]
+}
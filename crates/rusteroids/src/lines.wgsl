@@ -0,0 +1,94 @@
+// Thick line shader. Every instance is a segment between two consecutive
+// vertices of a line strip, expanded into a screen space quad.
+#include "CameraUniform"
+#include "ModelUniform"
+// Viewport size and half of the line width, in pixels
+#include "LineUniform"
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var<uniform> xform: ModelUniform;
+
+@group(2) @binding(0)
+var<uniform> line: LineUniform;
+
+struct SegmentInput {
+    @location(0) start: vec2<f32>,
+    @location(1) start_color: vec3<f32>,
+    @location(2) end: vec2<f32>,
+    @location(3) end_color: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // Position relative to the segment start in pixels, along and across it
+    @location(0) local: vec2<f32>,
+    @location(1) @interpolate(flat) length: f32,
+    @location(2) @interpolate(flat) start_color: vec3<f32>,
+    @location(3) @interpolate(flat) end_color: vec3<f32>,
+};
+
+fn to_pixels(clip: vec4<f32>) -> vec2<f32> {
+    return clip.xy / clip.w * line.viewport * 0.5;
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32,
+    segment: SegmentInput,
+) -> VertexOutput {
+    let start_clip = camera.view_proj * xform.matrix * vec4<f32>(segment.start, 0.0, 1.0);
+    let end_clip = camera.view_proj * xform.matrix * vec4<f32>(segment.end, 0.0, 1.0);
+    let start = to_pixels(start_clip);
+    let end = to_pixels(end_clip);
+
+    let length = distance(start, end);
+    var dir = vec2<f32>(1.0, 0.0);
+    if length > 0.0 {
+        dir = (end - start) / length;
+    }
+    let normal = vec2<f32>(-dir.y, dir.x);
+
+    // Quad corners, x along the segment (start to end) and y across it
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+
+    // Pad by half the width to fit the round caps, plus a pixel for anti-aliasing
+    let pad = line.half_width + 1.0;
+    let local = vec2<f32>(mix(-pad, length + pad, corner.x), corner.y * pad);
+    let pixel = start + dir * local.x + normal * local.y;
+    let origin = select(start_clip, end_clip, corner.x > 0.5);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(pixel / (line.viewport * 0.5) * origin.w, origin.z, origin.w);
+    out.local = local;
+    out.length = length;
+    out.start_color = segment.start_color;
+    out.end_color = segment.end_color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Distance to the segment rather than to the quad edges, which rounds
+    // both the caps and the joins between consecutive segments
+    let along = clamp(in.local.x, 0.0, in.length);
+    let distance = length(vec2<f32>(in.local.x - along, in.local.y));
+    let coverage = clamp(line.half_width + 0.5 - distance, 0.0, 1.0);
+
+    var ratio = 0.0;
+    if in.length > 0.0 {
+        ratio = along / in.length;
+    }
+    let color = mix(in.start_color, in.end_color, ratio);
+    return vec4<f32>(color * coverage, coverage);
+}
@@ -1,10 +1,45 @@
 use core::f32;
 
 use chrono::{DateTime, Local};
-use glam::{vec2, vec3, IVec2, Mat4, Vec2};
+use glam::{vec2, vec3, Mat4, Vec2, Vec3};
+
+use crate::asteroids::{AsteroidShape, AsteroidSize, ASTEROID_COLOR};
+use crate::camera::CameraEffects;
+use crate::mesh::Vertex;
+use crate::particles::ParticleSystem;
+use crate::svg::SvgMesh;
+use crate::utils::Rng;
+
+/// Size of the playfield in world units, centered on the origin. The camera
+/// scales it to fit the window, so the game is the same whatever its size.
+pub const PLAYFIELD: Vec2 = vec2(1024.0, 768.0);
 
 // Max velocity for the spaceship
 const MAX_VEL: f32 = 200.0;
+// Asteroids from this radius up shake the screen the most
+const LARGE_ASTEROID_RADIUS: f32 = 40.0;
+// Asteroids of each size in the field, each with a different outline
+const VARIANTS_PER_SIZE: u32 = 3;
+// Asteroids spawn at least this far from the center, where the spaceship spawns
+const SPAWN_CLEARANCE: f32 = 200.0;
+// Seconds before the spaceship respawns after being destroyed
+const RESPAWN_TIME: f32 = 2.0;
+// Outline of the spaceship, pointing up
+const SHIP_SVG: &str = include_str!("../assets/ship.svg");
+// Length of the spaceship, its outline is scaled to it
+const SHIP_SIZE: f32 = 40.0;
+// Distance from the spaceship's center to its farthest vertex
+const SHIP_RADIUS: f32 = 20.0;
+// Degrees off the pointer the spaceship still counts as facing it, and thrusts within
+const STEER_DEAD_ZONE: f32 = 5.0;
+const STEER_THRUST_ANGLE: f32 = 30.0;
+// Position of the spaceship's engine in model space, where the thrust flame comes from
+const ENGINE_POS: Vec2 = vec2(0.0, -10.0);
+// Ends of the base of the engine flame, on the spaceship's outline
+const FLAME_BASE: [Vec2; 2] = [vec2(-4.0, -14.0), vec2(4.0, -14.0)];
+// Longest the engine flame gets, from its base
+const FLAME_LENGTH: f32 = 12.0;
+const FLAME_COLOR: [f32; 3] = [1.0, 0.6, 0.2];
 
 /// Defines a simple bounding box structure
 struct BBox {
@@ -50,6 +85,12 @@ struct Movement {
 }
 
 impl Movement {
+    /// Translation and rotation of the element
+    pub fn model_matrix(&self) -> Mat4 {
+        Mat4::from_translation(vec3(self.pos.x, self.pos.y, 0.0))
+            .mul_mat4(&Mat4::from_rotation_z(self.dir.to_radians()))
+    }
+
     /// Updates position, direction, and velocity derivatives since last frame, according
     /// to elapsed time. Velocity is capped at MAX_VEL for all elements. Element will be positioned
     /// in mirroring position if it's out of the bounding box.
@@ -104,6 +145,40 @@ impl Default for Timer {
     }
 }
 
+/// Gameplay events the game reacts to with effects
+pub enum GameEvent {
+    /// An asteroid broke up, for now only when the spaceship crashes into it
+    AsteroidDestroyed {
+        position: Vec2,
+        velocity: Vec2,
+        radius: f32,
+    },
+    ShipDestroyed,
+}
+
+/// An asteroid drifting through the field, drawn with one of the generated shapes
+struct Asteroid {
+    movement: Movement,
+    shape: usize,
+}
+
+impl Asteroid {
+    /// An asteroid somewhere away from the center, drifting and spinning randomly
+    fn spawn(shape: usize, rng: &mut Rng) -> Self {
+        Self {
+            movement: Movement {
+                pos: Vec2::from_angle(rng.range(0.0, f32::consts::TAU))
+                    * rng.range(SPAWN_CLEARANCE, PLAYFIELD.min_element() / 2.0),
+                vel: Vec2::from_angle(rng.range(0.0, f32::consts::TAU)) * rng.range(20.0, 60.0),
+                dir: rng.range(0.0, 360.0),
+                cvel: rng.range(-40.0, 40.0),
+                ..Default::default()
+            },
+            shape,
+        }
+    }
+}
+
 pub enum Rotating {
     Left,
     Right,
@@ -117,27 +192,68 @@ pub struct Rusteroids {
     player_movement: Movement,
     player_is_accelerating: bool,
     player_is_rotating: Rotating,
+    // World position the spaceship steers towards, while the pointer is pressed
+    player_target: Option<Vec2>,
+    // Seconds left before respawning, while the spaceship is destroyed
+    player_respawn: Option<f32>,
+    ship: SvgMesh,
+    asteroid_shapes: Vec<AsteroidShape>,
+    asteroids: Vec<Asteroid>,
+    particles: ParticleSystem,
+    camera_effects: CameraEffects,
+    // Length of the engine flame as a fraction of FLAME_LENGTH, changing every frame
+    flame_flicker: f32,
+    rng: Rng,
 }
 
 impl Rusteroids {
     pub fn new() -> Self {
-        Self {
+        let mut rng = Rng::new(Local::now().timestamp_subsec_nanos());
+        let asteroid_shapes: Vec<AsteroidShape> = [
+            AsteroidSize::Large,
+            AsteroidSize::Medium,
+            AsteroidSize::Small,
+        ]
+        .into_iter()
+        .flat_map(|size| AsteroidShape::variants(size, VARIANTS_PER_SIZE, rng.next_u32()))
+        .collect();
+        let asteroids = (0..asteroid_shapes.len())
+            .map(|shape| Asteroid::spawn(shape, &mut rng))
+            .collect();
+        let mut rusteroids = Self {
             timer: Timer::default(),
             player_movement: Movement::default(),
             player_is_accelerating: false,
             player_is_rotating: Rotating::None,
-        }
+            player_target: None,
+            player_respawn: None,
+            ship: SvgMesh::parse(SHIP_SVG, SHIP_SIZE).expect("Invalid spaceship outline"),
+            asteroid_shapes,
+            asteroids,
+            particles: ParticleSystem::new(rng.next_u32()),
+            camera_effects: CameraEffects::default(),
+            flame_flicker: 1.0,
+            rng,
+        };
+        rusteroids.set_bounds(PLAYFIELD);
+        rusteroids
     }
 
-    /// Set game area bounds based on resolution
-    pub fn set_bounds(&mut self, res: IVec2) {
-        let half_res = vec2(res.x as f32 / 2.0, res.y as f32 / 2.0);
-        self.player_movement.bounds = BBox::with(-half_res, half_res);
+    /// Set game area bounds, centered on the origin
+    pub fn set_bounds(&mut self, size: Vec2) {
+        let half_size = size / 2.0;
+        self.player_movement.bounds = BBox::with(-half_size, half_size);
+        for asteroid in &mut self.asteroids {
+            asteroid.movement.bounds = BBox::with(-half_size, half_size);
+        }
     }
 
     /// Establishes a new frame to update elements position and mechanics
     pub fn tick(&mut self) {
         let elapsed = self.timer.tick().elapsed();
+        if let Some(target) = self.player_target {
+            self.steer_towards(target);
+        }
         self.player_movement.cvel = match self.player_is_rotating {
             Rotating::Left => 180.0_f32,
             Rotating::Right => -180.0_f32,
@@ -147,19 +263,186 @@ impl Rusteroids {
             true => 150.0,
             false => 0.0,
         };
-        self.player_movement.update(elapsed);
+        for asteroid in &mut self.asteroids {
+            asteroid.movement.update(elapsed);
+        }
+
+        if let Some(respawn) = self.player_respawn {
+            // Wait for the asteroids to clear the spawn point
+            let respawn = respawn - elapsed;
+            if respawn <= 0.0 && self.is_spawn_clear() {
+                self.player_respawn = None;
+                self.player_movement = Movement {
+                    bounds: std::mem::take(&mut self.player_movement.bounds),
+                    ..Default::default()
+                };
+            } else {
+                self.player_respawn = Some(respawn);
+            }
+        } else {
+            self.player_movement.update(elapsed);
+            if let Some(hit) = self.player_collision() {
+                self.trigger(GameEvent::ShipDestroyed);
+                self.player_respawn = Some(RESPAWN_TIME);
+                self.destroy_asteroid(hit);
+            }
+        }
+
+        if self.player_is_accelerating && self.is_player_alive() {
+            let rotation = Vec2::from_angle(self.player_movement.dir.to_radians());
+            self.particles.thrust(
+                self.player_movement.pos + rotation.rotate(ENGINE_POS),
+                rotation.rotate(Vec2::Y),
+                self.player_movement.vel,
+                elapsed,
+            );
+            self.flame_flicker = self.rng.range(0.5, 1.0);
+        }
+        self.particles.update(elapsed);
+        self.camera_effects.update(elapsed);
+    }
+
+    /// Reacts to a gameplay event with particles and camera effects. Large
+    /// asteroids and the ship's death shake the screen the most.
+    fn trigger(&mut self, event: GameEvent) {
+        match event {
+            GameEvent::AsteroidDestroyed {
+                position,
+                velocity,
+                radius,
+            } => {
+                // The sparks keep drifting with what's left of the asteroid
+                self.particles
+                    .explosion(position, velocity, radius, Vec3::from(ASTEROID_COLOR));
+                if radius >= LARGE_ASTEROID_RADIUS {
+                    self.camera_effects.add_trauma(0.5);
+                    self.camera_effects.punch(0.03);
+                } else {
+                    self.camera_effects.add_trauma(0.15);
+                }
+            }
+            GameEvent::ShipDestroyed => {
+                self.particles.debris(
+                    &self.ship.vertices,
+                    &self.ship.indices,
+                    Mat4::from_cols_array(&self.get_battleship_model_matrix()),
+                    self.player_movement.vel,
+                );
+                self.camera_effects.add_trauma(0.8);
+                self.camera_effects.punch(0.06);
+            }
+        }
+    }
+
+    /// Breaks up an asteroid, another one with the same outline drifts in to
+    /// take its place
+    fn destroy_asteroid(&mut self, index: usize) {
+        let asteroid = &mut self.asteroids[index];
+        let bounds = std::mem::take(&mut asteroid.movement.bounds);
+        let event = GameEvent::AsteroidDestroyed {
+            position: asteroid.movement.pos,
+            velocity: asteroid.movement.vel,
+            radius: self.asteroid_shapes[asteroid.shape].radius,
+        };
+        *asteroid = Asteroid::spawn(asteroid.shape, &mut self.rng);
+        asteroid.movement.bounds = bounds;
+        self.trigger(event);
+    }
+
+    /// The first asteroid with a vertex of the spaceship inside it
+    fn player_collision(&self) -> Option<usize> {
+        let ship = self.player_movement.model_matrix();
+        self.asteroids.iter().position(|asteroid| {
+            let shape = &self.asteroid_shapes[asteroid.shape];
+            let distance = asteroid.movement.pos.distance(self.player_movement.pos);
+            if distance > shape.radius + SHIP_RADIUS {
+                return false;
+            }
+            let to_asteroid = asteroid.movement.model_matrix().inverse() * ship;
+            self.ship.vertices.iter().any(|vertex| {
+                shape.contains(
+                    to_asteroid
+                        .transform_point3(vertex.position.extend(0.0))
+                        .truncate(),
+                )
+            })
+        })
+    }
+
+    fn is_spawn_clear(&self) -> bool {
+        self.asteroids.iter().all(|asteroid| {
+            let radius = self.asteroid_shapes[asteroid.shape].radius;
+            asteroid.movement.pos.length() > radius + SHIP_RADIUS * 2.0
+        })
+    }
+
+    pub fn is_player_alive(&self) -> bool {
+        self.player_respawn.is_none()
+    }
+
+    /// The spaceship's line list mesh
+    pub fn ship_mesh(&self) -> &SvgMesh {
+        &self.ship
+    }
+
+    /// The shape of every asteroid, in the same order as get_asteroid_model_matrices
+    pub fn asteroid_shapes(&self) -> impl Iterator<Item = &AsteroidShape> {
+        self.asteroids
+            .iter()
+            .map(|asteroid| &self.asteroid_shapes[asteroid.shape])
+    }
+
+    pub fn get_asteroid_model_matrices(&self) -> impl Iterator<Item = [f32; 16]> + '_ {
+        self.asteroids
+            .iter()
+            .map(|asteroid| asteroid.movement.model_matrix().to_cols_array())
+    }
+
+    pub fn get_camera_effect_matrix(&self) -> Mat4 {
+        self.camera_effects.matrix()
+    }
+
+    pub fn particles(&self) -> &ParticleSystem {
+        &self.particles
+    }
+
+    /// The engine flame in world space, as a line strip, while accelerating
+    pub fn get_flame_vertices(&self) -> Option<[Vertex; 3]> {
+        if !self.player_is_accelerating || !self.is_player_alive() {
+            return None;
+        }
+        let model_matrix = self.player_movement.model_matrix();
+        let tip = vec2(0.0, FLAME_BASE[0].y - FLAME_LENGTH * self.flame_flicker);
+        Some([FLAME_BASE[0], tip, FLAME_BASE[1]].map(|point| Vertex {
+            position: model_matrix.transform_point3(point.extend(0.0)).truncate(),
+            color: FLAME_COLOR,
+        }))
     }
 
     pub fn get_battleship_model_matrix(&self) -> [f32; 16] {
-        Mat4::from_translation(vec3(
-            self.player_movement.pos.x,
-            self.player_movement.pos.y,
-            0.0,
-        ))
-        .mul_mat4(&Mat4::from_rotation_z(
-            self.player_movement.dir.to_radians(),
-        ))
-        .to_cols_array()
+        self.player_movement.model_matrix().to_cols_array()
+    }
+
+    /// Steers the spaceship towards a world position, under the mouse or a finger,
+    /// instead of the keys. None gives the controls back to update_keys.
+    pub fn update_pointer(&mut self, target: Option<Vec2>) {
+        self.player_target = target;
+    }
+
+    /// Turns the spaceship to face target, thrusting once it roughly does
+    fn steer_towards(&mut self, target: Vec2) {
+        let to_target = target - self.player_movement.pos;
+        let facing = Vec2::from_angle(self.player_movement.dir.to_radians()).rotate(Vec2::Y);
+        let angle = facing.angle_to(to_target).to_degrees();
+        self.player_is_rotating = if angle > STEER_DEAD_ZONE {
+            Rotating::Left
+        } else if angle < -STEER_DEAD_ZONE {
+            Rotating::Right
+        } else {
+            Rotating::None
+        };
+        self.player_is_accelerating =
+            angle.abs() < STEER_THRUST_ANGLE && to_target.length() > SHIP_RADIUS;
     }
 
     pub fn update_keys(&mut self, w: bool, a: bool, d: bool) {
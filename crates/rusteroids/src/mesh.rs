@@ -1,73 +1,402 @@
 use std::ops::Range;
 
-use glam::Mat4;
-use wgpu::util::DeviceExt;
+use glam::{Mat4, Vec2};
 use wgpu::{Queue, RenderPass};
-use wgpu_utils::{format_of, VertexAttributeArray};
+use wgpu_utils::{UniformLayout, VertexAttributeArray, WgslStruct};
 
-use crate::utils::{common_layout_descriptor, Bindable, UniformBuffer};
+use crate::buffers::{DynamicBuffer, StreamingBuffer};
+use crate::utils::UniformBuffer;
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, VertexAttributeArray)]
+#[derive(
+    Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, VertexAttributeArray, WgslStruct,
+)]
+#[wgsl(vertex)]
 pub struct Vertex {
-    pub position: [f32; 2],
+    pub position: Vec2,
     pub color: [f32; 3],
 }
 
-pub struct Geometry {
-    vertex_buffer: Option<wgpu::Buffer>,
-    model_uniform: Option<UniformBuffer>,
-    geometry_size: usize,
+/// The model transform of a mesh as read by the shaders
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, UniformLayout, WgslStruct)]
+pub struct ModelUniform {
+    pub matrix: Mat4,
 }
 
-impl Bindable for Geometry {
-    fn layout_desc<'a>() -> wgpu::BindGroupLayoutDescriptor<'a> {
-        common_layout_descriptor(Some("mat4_layout_descriptor"))
-    }
+/// How the vertices, or the indices when there are some, are connected
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Primitive {
+    /// Each vertex connects to the next one
+    LineStrip,
+    /// Every pair of vertices is a separate segment
+    LineList,
+}
+
+/// Types usable in an index buffer
+pub trait Index: bytemuck::Pod + Into<u32> {
+    const FORMAT: wgpu::IndexFormat;
+}
+
+impl Index for u16 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint16;
+}
+
+impl Index for u32 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32;
+}
+
+struct IndexBuffer {
+    buffer: DynamicBuffer,
+    format: wgpu::IndexFormat,
+}
+
+pub struct Geometry {
+    vertex_buffer: DynamicBuffer,
+    index_buffer: Option<IndexBuffer>,
+    model_uniform: UniformBuffer<ModelUniform>,
+    primitive: Primitive,
+    // Ranges of vertices, or indices when indexed, drawn separately
+    strips: Vec<Range<u32>>,
+    // Indexed vertices laid out as line strips, as thick lines can't be indexed
+    line_strips: Option<(DynamicBuffer, Vec<Range<u32>>)>,
 }
 
 impl Geometry {
-    pub fn new(
+    /// Creates a Geometry holding several disjoint line strips in the same vertex buffer,
+    /// each given by its range of vertices
+    pub fn with_strips(
         data: &[Vertex],
+        strips: Vec<Range<u32>>,
         device: &wgpu::Device,
         bind_group_layout: &wgpu::BindGroupLayout,
         mesh_index: u32,
     ) -> Self {
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("vertex{}_buffer", mesh_index)),
-            contents: bytemuck::cast_slice(data),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-        let model = Mat4::IDENTITY.to_cols_array();
+        let vertex_buffer = DynamicBuffer::new(
+            device,
+            &format!("vertex{}_buffer", mesh_index),
+            wgpu::BufferUsages::VERTEX,
+            bytemuck::cast_slice(data),
+        );
         let model_uniform = UniformBuffer::new(
-            &model,
+            &ModelUniform {
+                matrix: Mat4::IDENTITY,
+            },
             device,
             bind_group_layout,
             &format!("mesh{}", mesh_index),
         );
         Self {
-            vertex_buffer: Some(vertex_buffer),
-            model_uniform: Some(model_uniform),
-            geometry_size: data.len(),
+            vertex_buffer,
+            index_buffer: None,
+            model_uniform,
+            primitive: Primitive::LineStrip,
+            strips,
+            line_strips: None,
         }
     }
 
-    pub fn update_buffer(&mut self, queue: &Queue, model_matrix: &[f32; 16]) {
-        if let Some(uniform) = &self.model_uniform {
-            uniform.update_buffer(model_matrix, queue);
+    /// Creates a Geometry drawn through an index buffer, either as a single line strip
+    /// or as a line list, so disjoint outlines can share a mesh without repeating vertices
+    pub fn with_indices<I: Index>(
+        data: &[Vertex],
+        indices: &[I],
+        primitive: Primitive,
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        mesh_index: u32,
+    ) -> Self {
+        let index_buffer = DynamicBuffer::new(
+            device,
+            &format!("index{}_buffer", mesh_index),
+            wgpu::BufferUsages::INDEX,
+            bytemuck::cast_slice(indices),
+        );
+        let (line_vertices, line_ranges) = line_strips(data, indices, primitive);
+        let line_buffer = DynamicBuffer::new(
+            device,
+            &format!("line{}_buffer", mesh_index),
+            wgpu::BufferUsages::VERTEX,
+            bytemuck::cast_slice(&line_vertices),
+        );
+        Self {
+            index_buffer: Some(IndexBuffer {
+                buffer: index_buffer,
+                format: I::FORMAT,
+            }),
+            primitive,
+            line_strips: Some((line_buffer, line_ranges)),
+            ..Self::with_strips(
+                data,
+                std::iter::once(0..indices.len() as u32).collect(),
+                device,
+                bind_group_layout,
+                mesh_index,
+            )
         }
     }
 
+    pub fn primitive(&self) -> Primitive {
+        self.primitive
+    }
+
+    /// Replaces the vertices and strips of a Geometry created with with_strips,
+    /// growing its vertex buffer when they don't fit
+    pub fn update_strips(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &Queue,
+        data: &[Vertex],
+        strips: Vec<Range<u32>>,
+    ) {
+        debug_assert!(self.index_buffer.is_none(), "Geometry is indexed");
+        self.vertex_buffer
+            .write(device, queue, bytemuck::cast_slice(data));
+        self.strips = strips;
+    }
+
+    pub fn update_buffer(&mut self, queue: &Queue, model_matrix: &[f32; 16]) {
+        self.model_uniform.update_buffer(
+            &ModelUniform {
+                matrix: Mat4::from_cols_array(model_matrix),
+            },
+            queue,
+        );
+    }
+
     pub fn render(&self, pass: &mut RenderPass<'_>, instances: Range<u32>) {
-        if let Some(buffer) = &self.vertex_buffer {
-            if let Some(uniform) = &self.model_uniform {
-                pass.set_vertex_buffer(0, buffer.slice(..));
-                uniform.bind(pass, 1);
-                pass.draw(0..self.geometry_size as u32, instances);
-            } else {
-                todo!()
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        self.model_uniform.bind(pass, 1);
+        match &self.index_buffer {
+            Some(index_buffer) => {
+                pass.set_index_buffer(index_buffer.buffer.slice(..), index_buffer.format);
+                for strip in &self.strips {
+                    pass.draw_indexed(strip.clone(), 0, instances.clone());
+                }
+            }
+            None => {
+                for strip in &self.strips {
+                    pass.draw(strip.clone(), instances.clone());
+                }
+            }
+        }
+    }
+
+    /// Renders the geometry as thick lines, with one instance per segment of each strip.
+    /// Expects the LineRenderer pipeline to be bound.
+    pub fn render_lines(&self, pass: &mut RenderPass<'_>) {
+        let (buffer, strips) = match &self.line_strips {
+            Some((buffer, strips)) => (buffer, strips),
+            None => (&self.vertex_buffer, &self.strips),
+        };
+        let stride = std::mem::size_of::<Vertex>() as wgpu::BufferAddress;
+        pass.set_vertex_buffer(0, buffer.slice(..));
+        pass.set_vertex_buffer(1, buffer.slice(stride..));
+        self.model_uniform.bind(pass, 1);
+        for instances in segments(strips) {
+            pass.draw(0..6, instances);
+        }
+    }
+}
+
+/// Line strips rebuilt every frame, such as effects that deform or flicker, placed
+/// directly in the world. They share a single streaming buffer instead of a
+/// Geometry each.
+pub struct LineStream {
+    vertices: StreamingBuffer<Vertex>,
+    strips: Vec<Range<u32>>,
+    // Always the identity, vertices are pushed in world space
+    model_uniform: UniformBuffer<ModelUniform>,
+}
+
+impl LineStream {
+    pub fn new(device: &wgpu::Device, bind_group_layout: &wgpu::BindGroupLayout) -> Self {
+        Self {
+            vertices: StreamingBuffer::new(
+                device,
+                "stream_vertex_buffer",
+                wgpu::BufferUsages::VERTEX,
+                256,
+            ),
+            strips: Vec::new(),
+            model_uniform: UniformBuffer::new(
+                &ModelUniform {
+                    matrix: Mat4::IDENTITY,
+                },
+                device,
+                bind_group_layout,
+                "stream",
+            ),
+        }
+    }
+
+    /// Adds line strips for the current frame, each given by its range of vertices
+    pub fn push(&mut self, data: &[Vertex], strips: impl IntoIterator<Item = Range<u32>>) {
+        let offset = self.vertices.push(data).start;
+        self.strips.extend(
+            strips
+                .into_iter()
+                .map(|strip| strip.start + offset..strip.end + offset),
+        );
+    }
+
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &Queue) {
+        self.vertices.upload(device, queue);
+    }
+
+    /// Drops this frame's strips, after rendering them
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.strips.clear();
+    }
+
+    /// Renders the strips, expects a line strip Gadget to be bound
+    pub fn render(&self, pass: &mut RenderPass<'_>) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        pass.set_vertex_buffer(0, self.vertices.slice(..));
+        self.model_uniform.bind(pass, 1);
+        for strip in &self.strips {
+            pass.draw(strip.clone(), 0..1);
+        }
+    }
+
+    /// Renders the strips as thick lines, see Geometry::render_lines
+    pub fn render_lines(&self, pass: &mut RenderPass<'_>) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let stride = std::mem::size_of::<Vertex>() as wgpu::BufferAddress;
+        pass.set_vertex_buffer(0, self.vertices.slice(..));
+        pass.set_vertex_buffer(1, self.vertices.slice(stride..));
+        self.model_uniform.bind(pass, 1);
+        for instances in segments(&self.strips) {
+            pass.draw(0..6, instances);
+        }
+    }
+}
+
+/// The instances drawing the segments of each strip, one per segment starting at
+/// its first vertex. Strips of a single vertex have none.
+fn segments(strips: &[Range<u32>]) -> impl Iterator<Item = Range<u32>> + '_ {
+    strips
+        .iter()
+        .filter(|strip| strip.len() > 1)
+        .map(|strip| strip.start..strip.end - 1)
+}
+
+/// Lays out indexed vertices as line strips. Consecutive segments of a line list
+/// sharing an end are chained into the same strip.
+fn line_strips<I: Index>(
+    data: &[Vertex],
+    indices: &[I],
+    primitive: Primitive,
+) -> (Vec<Vertex>, Vec<Range<u32>>) {
+    let vertex = |index: I| data[index.into() as usize];
+    match primitive {
+        Primitive::LineStrip => (
+            indices.iter().copied().map(vertex).collect(),
+            std::iter::once(0..indices.len() as u32).collect(),
+        ),
+        Primitive::LineList => {
+            let mut vertices = Vec::new();
+            let mut strips: Vec<Range<u32>> = Vec::new();
+            let mut last = None;
+            for segment in indices.chunks_exact(2) {
+                let (start, end) = (segment[0].into(), segment[1].into());
+                if last != Some(start) {
+                    let first = vertices.len() as u32;
+                    strips.push(first..first);
+                    vertices.push(vertex(segment[0]));
+                }
+                vertices.push(vertex(segment[1]));
+                last = Some(end);
+                if let Some(strip) = strips.last_mut() {
+                    strip.end = vertices.len() as u32;
+                }
             }
-        } else {
-            todo!()
+            (vertices, strips)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+
+    /// Vertices at x = 0, 1, 2... so laid out vertices can be told apart by position
+    fn vertices(count: usize) -> Vec<Vertex> {
+        (0..count)
+            .map(|i| Vertex {
+                position: vec2(i as f32, 0.0),
+                color: [1.0, 1.0, 1.0],
+            })
+            .collect()
+    }
+
+    fn xs(vertices: &[Vertex]) -> Vec<f32> {
+        vertices.iter().map(|vertex| vertex.position.x).collect()
+    }
+
+    #[test]
+    fn single_strip() {
+        let data = vertices(4);
+        let (laid_out, strips) = line_strips(&data, &[3u16, 1, 2], Primitive::LineStrip);
+        assert_eq!(xs(&laid_out), [3.0, 1.0, 2.0]);
+        assert_eq!(strips.len(), 1);
+        assert_eq!(strips[0], 0..3);
+
+        // A closed line list is chained into one strip
+        let square = [0u16, 1, 1, 2, 2, 3, 3, 0];
+        let (laid_out, strips) = line_strips(&data, &square, Primitive::LineList);
+        assert_eq!(xs(&laid_out), [0.0, 1.0, 2.0, 3.0, 0.0]);
+        assert_eq!(strips.len(), 1);
+        assert_eq!(strips[0], 0..5);
+        assert!(segments(&strips).eq(std::iter::once(0..4)));
+    }
+
+    #[test]
+    fn disjoint_strips() {
+        // Segments not sharing an end start a new strip, even when a later one
+        // continues an earlier strip
+        let data = vertices(6);
+        let indices = [0u16, 1, 1, 2, 3, 4, 2, 5];
+        let (laid_out, strips) = line_strips(&data, &indices, Primitive::LineList);
+        assert_eq!(xs(&laid_out), [0.0, 1.0, 2.0, 3.0, 4.0, 2.0, 5.0]);
+        assert_eq!(strips, [0..3, 3..5, 5..7]);
+        assert_eq!(segments(&strips).collect::<Vec<_>>(), [0..2, 3..4, 5..6]);
+    }
+
+    #[test]
+    fn single_vertex_strips() {
+        let data = vertices(2);
+        let (laid_out, strips) = line_strips(&data, &[1u16], Primitive::LineStrip);
+        assert_eq!(xs(&laid_out), [1.0]);
+        assert_eq!(strips.len(), 1);
+        assert_eq!(strips[0], 0..1);
+        assert_eq!(segments(&strips).count(), 0);
+        assert_eq!(
+            segments(&[0..2, 2..3, 3..3, 3..5]).collect::<Vec<_>>(),
+            [0..1, 3..4]
+        );
+    }
+
+    #[test]
+    fn index_types() {
+        let data = vertices(4);
+        let list_u16 = line_strips(&data, &[0u16, 1, 1, 2, 3, 0], Primitive::LineList);
+        let list_u32 = line_strips(&data, &[0u32, 1, 1, 2, 3, 0], Primitive::LineList);
+        assert_eq!(xs(&list_u16.0), xs(&list_u32.0));
+        assert_eq!(list_u16.1, list_u32.1);
+        assert_eq!(list_u32.1, [0..3, 3..5]);
+        assert_eq!(u16::FORMAT, wgpu::IndexFormat::Uint16);
+        assert_eq!(u32::FORMAT, wgpu::IndexFormat::Uint32);
+
+        // u32 indices reach past the range of u16
+        let data = vertices(70_000);
+        let (laid_out, _) = line_strips(&data, &[69_999u32, 0], Primitive::LineStrip);
+        assert_eq!(xs(&laid_out), [69_999.0, 0.0]);
+    }
+}
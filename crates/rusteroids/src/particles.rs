@@ -0,0 +1,380 @@
+use std::f32::consts::TAU;
+
+use glam::{vec2, Mat4, Vec2, Vec3};
+use wgpu::RenderPass;
+use wgpu_utils::{VertexAttributeArray, WgslStruct};
+
+use crate::gadget::{Blend, Gadget, GadgetBuilder, GadgetError};
+use crate::mesh::{Index, Vertex};
+use crate::shaders::Shader;
+use crate::utils::Rng;
+
+/// Maximum number of live particles, new ones aren't emitted past it
+const MAX_PARTICLES: usize = 4096;
+/// Thrust particles emitted per second while accelerating
+const THRUST_RATE: f32 = 120.0;
+/// Sparks are drawn as streaks covering the distance travelled in this many seconds
+const STREAK_TIME: f32 = 0.02;
+
+/// A particle as read by the particle shader, one per instance
+#[repr(C)]
+#[derive(
+    Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, VertexAttributeArray, WgslStruct,
+)]
+#[vertex(instance)]
+#[wgsl(vertex)]
+pub struct ParticleInstance {
+    pub center: [f32; 2],
+    /// From the center to the head of the segment
+    pub half_extent: [f32; 2],
+    pub color: [f32; 4],
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Shape {
+    /// A short streak along the velocity
+    Spark,
+    /// A rotating line segment, for debris
+    Segment { half_extent: Vec2, spin: f32 },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    position: Vec2,
+    velocity: Vec2,
+    /// Exponential decay rate of the velocity, per second
+    drag: f32,
+    age: f32,
+    lifetime: f32,
+    start_color: Vec3,
+    end_color: Vec3,
+    shape: Shape,
+}
+
+impl Particle {
+    fn update(&mut self, elapsed_time: f32) {
+        self.age += elapsed_time;
+        self.velocity *= (-self.drag * elapsed_time).exp();
+        self.position += self.velocity * elapsed_time;
+        if let Shape::Segment { half_extent, spin } = &mut self.shape {
+            *half_extent = Vec2::from_angle(*spin * elapsed_time).rotate(*half_extent);
+        }
+    }
+
+    /// Colour fades from start to end colour while becoming transparent
+    fn instance(&self) -> ParticleInstance {
+        let t = (self.age / self.lifetime).clamp(0.0, 1.0);
+        let color = self.start_color.lerp(self.end_color, t);
+        let half_extent = match self.shape {
+            Shape::Spark => {
+                // Slow sparks keep a minimum length, still ones have no direction to streak along
+                let streak = self.velocity * STREAK_TIME / 2.0;
+                streak.normalize_or(Vec2::X) * streak.length().max(0.5)
+            }
+            Shape::Segment { half_extent, .. } => half_extent,
+        };
+        ParticleInstance {
+            center: self.position.to_array(),
+            half_extent: half_extent.to_array(),
+            color: color.extend(1.0 - t).to_array(),
+        }
+    }
+}
+
+/// Simulates the particles on the CPU, emitted by a few effect specific emitters.
+/// Particles move with drag and fade out over their lifetime.
+/// TODO: move the simulation to a compute shader if the particle count grows
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    rng: Rng,
+    // Fraction of a thrust particle carried over to the next frame
+    thrust_remainder: f32,
+}
+
+impl ParticleSystem {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            particles: Vec::new(),
+            rng: Rng::new(seed),
+            thrust_remainder: 0.0,
+        }
+    }
+
+    /// Ages and moves the particles, removing the expired ones
+    pub fn update(&mut self, elapsed_time: f32) {
+        self.particles.iter_mut().for_each(|particle| {
+            particle.update(elapsed_time);
+        });
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+    }
+
+    /// Emits the exhaust flame of a ship accelerating towards direction, from the
+    /// position of its engine, for the time elapsed since the last frame
+    pub fn thrust(
+        &mut self,
+        position: Vec2,
+        direction: Vec2,
+        ship_velocity: Vec2,
+        elapsed_time: f32,
+    ) {
+        let count = THRUST_RATE * elapsed_time + self.thrust_remainder;
+        self.thrust_remainder = count.fract();
+        for _ in 0..count as u32 {
+            let spread = self.rng.range(-0.3, 0.3);
+            let speed = self.rng.range(120.0, 200.0);
+            let particle = Particle {
+                position,
+                velocity: ship_velocity - Vec2::from_angle(spread).rotate(direction) * speed,
+                drag: 3.0,
+                age: 0.0,
+                lifetime: self.rng.range(0.2, 0.45),
+                start_color: Vec3::new(1.0, 0.9, 0.5),
+                end_color: Vec3::new(0.8, 0.15, 0.0),
+                shape: Shape::Spark,
+            };
+            self.emit(particle);
+        }
+    }
+
+    /// Emits a burst of sparks flying out of position, the more the bigger the radius
+    pub fn explosion(&mut self, position: Vec2, velocity: Vec2, radius: f32, color: Vec3) {
+        let count = 8 + (radius * 0.75) as u32;
+        for _ in 0..count {
+            let direction = Vec2::from_angle(self.rng.range(0.0, TAU));
+            let speed = self.rng.range(40.0, 160.0);
+            let particle = Particle {
+                position: position + direction * self.rng.range(0.0, radius / 2.0),
+                velocity: velocity + direction * speed,
+                drag: 1.5,
+                age: 0.0,
+                lifetime: self.rng.range(0.5, 1.2),
+                start_color: Vec3::ONE,
+                end_color: color * 0.3,
+                shape: Shape::Spark,
+            };
+            self.emit(particle);
+        }
+    }
+
+    /// Breaks an indexed line list, placed in the world by transform, into a spinning
+    /// line segment per edge, drifting apart from its center
+    pub fn debris<I: Index>(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[I],
+        transform: Mat4,
+        velocity: Vec2,
+    ) {
+        let center = transform.transform_point3(Vec3::ZERO).truncate();
+        for segment in indices.chunks_exact(2) {
+            let edge = [segment[0], segment[1]].map(|index| vertices[index.into() as usize]);
+            let [start, end] = edge.map(|vertex| {
+                transform
+                    .transform_point3(vertex.position.extend(0.0))
+                    .truncate()
+            });
+            let midpoint = (start + end) / 2.0;
+            let outwards = (midpoint - center).normalize_or_zero();
+            let particle = Particle {
+                position: midpoint,
+                velocity: velocity
+                    + outwards * self.rng.range(20.0, 60.0)
+                    + vec2(self.rng.range(-10.0, 10.0), self.rng.range(-10.0, 10.0)),
+                drag: 0.5,
+                age: 0.0,
+                lifetime: self.rng.range(1.5, 3.0),
+                start_color: Vec3::from(edge[0].color),
+                end_color: Vec3::ZERO,
+                shape: Shape::Segment {
+                    half_extent: (end - start) / 2.0,
+                    spin: self.rng.range(-4.0, 4.0),
+                },
+            };
+            self.emit(particle);
+        }
+    }
+
+    pub fn instances(&self) -> Vec<ParticleInstance> {
+        self.particles.iter().map(Particle::instance).collect()
+    }
+
+    fn emit(&mut self, particle: Particle) {
+        if self.particles.len() < MAX_PARTICLES {
+            self.particles.push(particle);
+        }
+    }
+}
+
+/// Renders every particle in a single instanced draw of line segments,
+/// blended additively so overlapping particles glow
+pub struct ParticleRenderer {
+    gadget: Gadget,
+    instance_buffer: wgpu::Buffer,
+    count: u32,
+}
+
+impl ParticleRenderer {
+    /// Creates the particle pipeline. The camera binding is expected to match
+    /// the one used by the default Gadget.
+    pub fn new(
+        device: &wgpu::Device,
+        shader: &Shader,
+        camera_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        format_features: wgpu::TextureFormatFeatures,
+        sample_count: u32,
+    ) -> Result<Self, GadgetError> {
+        let gadget = GadgetBuilder::new(&shader.module(device), format)
+            .label("Particle Pipeline")
+            .format_features(format_features)
+            .vertex_layout(ParticleInstance::desc())
+            .bind_group_layout(camera_layout)
+            .topology(wgpu::PrimitiveTopology::LineList)
+            .blend(Blend::Additive)
+            .sample_count(sample_count)
+            .build(device, &shader.reflect()?)?;
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle_instance_buffer"),
+            size: (MAX_PARTICLES * std::mem::size_of::<ParticleInstance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Ok(Self {
+            gadget,
+            instance_buffer,
+            count: 0,
+        })
+    }
+
+    /// Uploads the live particles for this frame
+    pub fn update_buffer(&mut self, queue: &wgpu::Queue, particles: &ParticleSystem) {
+        let instances = particles.instances();
+        self.count = instances.len() as u32;
+        if !instances.is_empty() {
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        }
+    }
+
+    /// Draws the particles, the camera needs to be bound already
+    pub fn render(&self, pass: &mut RenderPass<'_>) {
+        if self.count == 0 {
+            return;
+        }
+        pass.set_pipeline(&self.gadget);
+        pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        pass.draw(0..2, 0..self.count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spark(velocity: Vec2, drag: f32, lifetime: f32) -> Particle {
+        Particle {
+            position: Vec2::ZERO,
+            velocity,
+            drag,
+            age: 0.0,
+            lifetime,
+            start_color: Vec3::ONE,
+            end_color: Vec3::new(1.0, 0.0, 0.0),
+            shape: Shape::Spark,
+        }
+    }
+
+    #[test]
+    fn update_applies_velocity_and_drag() {
+        let mut particles = ParticleSystem::new(1);
+        particles.emit(spark(vec2(10.0, 0.0), 0.0, 1.0));
+        particles.emit(spark(vec2(0.0, 10.0), 2.0, 2.0));
+        particles.update(0.5);
+
+        let [free, dragged] = [particles.particles[0], particles.particles[1]];
+        assert_eq!(free.position, vec2(5.0, 0.0));
+        assert_eq!(free.velocity, vec2(10.0, 0.0));
+        // Drag decays the velocity before it moves the particle
+        let decayed = 10.0 * (-1.0f32).exp();
+        assert_eq!(dragged.velocity, vec2(0.0, decayed));
+        assert_eq!(dragged.position, vec2(0.0, decayed * 0.5));
+
+        // Particles are dropped once their age reaches their lifetime
+        particles.update(0.5);
+        assert_eq!(particles.particles.len(), 1);
+        assert_eq!(particles.particles[0].lifetime, 2.0);
+        particles.update(1.0);
+        assert!(particles.instances().is_empty());
+    }
+
+    #[test]
+    fn thrust_is_frame_rate_independent() {
+        // Emitting for a second gives the same count whatever the frame time,
+        // fractions of a particle being carried over to the next frame
+        for frames in [32, 60, 128, 240] {
+            let mut particles = ParticleSystem::new(1);
+            for _ in 0..frames {
+                particles.thrust(Vec2::ZERO, Vec2::Y, Vec2::ZERO, 1.0 / frames as f32);
+            }
+            let count = particles.particles.len() as f32;
+            assert!(
+                (count - THRUST_RATE).abs() <= 1.0,
+                "{count} at {frames} fps"
+            );
+        }
+
+        // Less than a particle per frame still emits one every few frames
+        let mut particles = ParticleSystem::new(1);
+        particles.thrust(Vec2::ZERO, Vec2::Y, Vec2::ZERO, 0.5 / THRUST_RATE);
+        assert_eq!(particles.particles.len(), 0);
+        particles.thrust(Vec2::ZERO, Vec2::Y, Vec2::ZERO, 0.5 / THRUST_RATE);
+        assert_eq!(particles.particles.len(), 1);
+    }
+
+    #[test]
+    fn instances_fade_over_lifetime() {
+        let mut particle = spark(vec2(100.0, 0.0), 0.0, 2.0);
+        let instance = particle.instance();
+        assert_eq!(instance.color, [1.0, 1.0, 1.0, 1.0]);
+        // Sparks streak along their velocity
+        assert_eq!(instance.half_extent, [100.0 * STREAK_TIME / 2.0, 0.0]);
+
+        particle.age = 1.0;
+        assert_eq!(particle.instance().color, [1.0, 0.5, 0.5, 0.5]);
+        particle.age = 3.0;
+        assert_eq!(particle.instance().color, [1.0, 0.0, 0.0, 0.0]);
+
+        // Slow and still sparks are still visible
+        particle.velocity = vec2(0.0, 1.0);
+        assert_eq!(particle.instance().half_extent, [0.0, 0.5]);
+        particle.velocity = Vec2::ZERO;
+        assert_eq!(particle.instance().half_extent, [0.5, 0.0]);
+    }
+
+    #[test]
+    fn emitter_counts() {
+        let mut particles = ParticleSystem::new(1);
+        particles.explosion(Vec2::ZERO, Vec2::ZERO, 40.0, Vec3::ONE);
+        assert_eq!(particles.particles.len(), 8 + 30);
+
+        // A segment for every edge of the line list, in its colour
+        let color = [0.2, 0.4, 0.6];
+        let vertices = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| Vertex {
+            position: vec2(x, y),
+            color,
+        });
+        let mut particles = ParticleSystem::new(1);
+        let square: [u16; 8] = [0, 1, 1, 2, 2, 3, 3, 0];
+        particles.debris(&vertices, &square, Mat4::IDENTITY, Vec2::ZERO);
+        assert_eq!(particles.particles.len(), 4);
+        let instance = particles.instances()[0];
+        assert_eq!(instance.color, [0.2, 0.4, 0.6, 1.0]);
+        assert_eq!(instance.center, [0.0, -1.0]);
+        assert_eq!(instance.half_extent, [1.0, 0.0]);
+
+        // Emitters stop at the maximum number of particles
+        let mut particles = ParticleSystem::new(1);
+        particles.explosion(Vec2::ZERO, Vec2::ZERO, 10_000.0, Vec3::ONE);
+        assert_eq!(particles.particles.len(), MAX_PARTICLES);
+    }
+}
@@ -0,0 +1,31 @@
+// Particle shader. Every instance is a particle drawn as a single line segment
+// around its center, the first vertex at its tail and the second at its head.
+#include "CameraUniform"
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// Half extent goes from the center to the head of the segment
+#include "ParticleInstance"
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32,
+    particle: ParticleInstance,
+) -> VertexOutput {
+    let side = select(-1.0, 1.0, index == 1u);
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(particle.center + side * particle.half_extent, 0.0, 1.0);
+    out.color = particle.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
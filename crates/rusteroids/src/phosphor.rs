@@ -0,0 +1,194 @@
+use wgpu::{CommandEncoder, RenderPass};
+use wgpu_utils::Bindable;
+
+use crate::gadget::{Blend, Gadget, GadgetBuilder, GadgetError};
+use crate::shaders::Shader;
+
+/// Settings for the phosphor persistence effect
+#[derive(Clone, Copy, Debug)]
+pub struct PhosphorSettings {
+    pub enabled: bool,
+    /// Fraction of the accumulated brightness lost every frame, in [0, 1].
+    /// Lower values give longer trails.
+    pub decay: f32,
+}
+
+impl Default for PhosphorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            decay: 0.25,
+        }
+    }
+}
+
+/// The accumulation buffer, read texel by texel when blitting
+#[derive(Bindable)]
+struct PhosphorBindings<'a> {
+    #[texture(sample_type = "unfilterable_float", visibility = "fragment")]
+    accumulation: &'a wgpu::TextureView,
+}
+
+/// Layout of the accumulation buffer, bound to group 0 of the blit pipeline
+pub const PHOSPHOR_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> =
+    wgpu::BindGroupLayoutDescriptor {
+        label: Some("phosphor_layout"),
+        entries: PhosphorBindings::ENTRIES,
+    };
+
+/// An accumulation buffer mimicking the persistence of a vector CRT.
+/// Instead of clearing to black, every frame fades the previous one by
+/// the decay rate and draws on top of it, so moving elements leave
+/// fading trails. The result is then blitted into the surface.
+///
+/// When multisampling, the multisampled colour target keeps the accumulated
+/// frames instead, and is resolved straight into the surface.
+pub struct Phosphor {
+    fade_gadget: Gadget,
+    blit_gadget: Gadget,
+    layout: wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    needs_clear: bool,
+}
+
+impl Phosphor {
+    pub fn new(
+        device: &wgpu::Device,
+        shader: &Shader,
+        format: wgpu::TextureFormat,
+        format_features: wgpu::TextureFormatFeatures,
+        size: winit::dpi::PhysicalSize<u32>,
+        sample_count: u32,
+    ) -> Result<Self, GadgetError> {
+        let layout = device.create_bind_group_layout(&PHOSPHOR_LAYOUT);
+        let module = shader.module(device);
+        let reflection = shader.reflect()?;
+
+        // Fading computes dst * constant - src, with the constant set per frame
+        // from the decay rate
+        let fade_blend = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::Constant,
+            operation: wgpu::BlendOperation::ReverseSubtract,
+        };
+        let fade_gadget = GadgetBuilder::new(&module, format)
+            .label("Phosphor Fade Pipeline")
+            .format_features(format_features)
+            .entry_points("vs_fullscreen", "fs_fade")
+            .blend(Blend::Custom(wgpu::BlendState {
+                color: fade_blend,
+                alpha: fade_blend,
+            }))
+            .sample_count(sample_count)
+            .build(device, &reflection)?;
+        let blit_gadget = GadgetBuilder::new(&module, format)
+            .label("Phosphor Blit Pipeline")
+            .format_features(format_features)
+            .entry_points("vs_fullscreen", "fs_blit")
+            .bind_group_layout(&layout)
+            .build(device, &reflection)?;
+
+        let (view, bind_group) = create_target(device, &layout, format, size);
+        Ok(Self {
+            fade_gadget,
+            blit_gadget,
+            layout,
+            format,
+            view,
+            bind_group,
+            needs_clear: true,
+        })
+    }
+
+    /// Clears the accumulated frames on the next pass, so stale trails
+    /// aren't shown when the effect is enabled again
+    pub fn clear(&mut self) {
+        self.needs_clear = true;
+    }
+
+    /// Recreates the accumulation buffer to match the new surface size
+    pub fn resize(&mut self, device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>) {
+        (self.view, self.bind_group) = create_target(device, &self.layout, self.format, size);
+        self.needs_clear = true;
+    }
+
+    /// The texture view the scene should be rendered into when not multisampling
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// The load operation for the accumulation buffer. Only clears it when it's
+    /// been (re)created or cleared.
+    pub fn load_op(&mut self) -> wgpu::LoadOp<wgpu::Color> {
+        if std::mem::take(&mut self.needs_clear) {
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+        } else {
+            wgpu::LoadOp::Load
+        }
+    }
+
+    /// Fades the previous frame by the decay rate, should be called at the
+    /// beginning of the pass rendering into the accumulation buffer.
+    pub fn fade(&self, pass: &mut RenderPass<'_>, decay: f32) {
+        let keep = (1.0 - decay.clamp(0.0, 1.0)) as f64;
+        pass.set_pipeline(&self.fade_gadget);
+        pass.set_blend_constant(wgpu::Color {
+            r: keep,
+            g: keep,
+            b: keep,
+            a: keep,
+        });
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Copies the accumulation buffer into the output view
+    pub fn blit(&self, encoder: &mut CommandEncoder, output: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Phosphor Blit Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.blit_gadget);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_target(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> (wgpu::TextureView, wgpu::BindGroup) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("phosphor_texture"),
+        size: wgpu::Extent3d {
+            width: size.width.max(1),
+            height: size.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = PhosphorBindings {
+        accumulation: &view,
+    }
+    .create_bind_group(device, layout, Some("phosphor_bind_group"));
+    (view, bind_group)
+}
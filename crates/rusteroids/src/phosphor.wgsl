@@ -0,0 +1,25 @@
+// Phosphor persistence shaders. Both passes draw a single fullscreen triangle.
+
+// Smallest amount subtracted from the accumulation buffer every frame, so
+// dim trails reach black instead of getting stuck on 8-bit rounding.
+const FADE_FLOOR: f32 = 2.0 / 255.0;
+
+@group(0) @binding(0)
+var accumulation: texture_2d<f32>;
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Blended with the accumulation buffer as `dst * decay - src`
+@fragment
+fn fs_fade() -> @location(0) vec4<f32> {
+    return vec4<f32>(FADE_FLOOR);
+}
+
+@fragment
+fn fs_blit(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(accumulation, vec2<i32>(position.xy), 0);
+}
@@ -1,18 +1,47 @@
-use std::{iter, ops::Deref};
+use std::{
+    iter,
+    ops::{Deref, Range},
+};
 
 use std::collections::HashMap;
-use winit::{event::*, window::Window};
+use winit::{
+    event::*,
+    keyboard::{KeyCode, PhysicalKey},
+    window::Window,
+};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-use crate::camera::OrthoCamera;
+use crate::camera::{Camera, Origin, OrthoCamera};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::capture::{Capture, RecordingFormat};
+use crate::gadget::{Gadget, GadgetBuilder, GadgetError};
+use crate::lines::{segment_layouts, LineRenderer, LINE_LAYOUT};
+use crate::particles::{ParticleInstance, ParticleRenderer, ParticleSystem};
+use crate::phosphor::{Phosphor, PHOSPHOR_LAYOUT};
+use crate::settings::RenderSettings;
+use crate::shaders::{Shader, Shaders};
 use crate::{
-    mesh::{Geometry, Vertex},
-    utils::UniformBinding,
+    mesh::{Geometry, LineStream, Primitive, Vertex},
+    utils::{UniformBinding, UniformBindings},
 };
 
-use wgpu_utils::VertexAttributeArray;
+use wgpu_utils::reflect::ReflectionError;
+use wgpu_utils::{Bindable, VertexAttributeArray};
+
+/// Layout of the camera uniform, bound to group 0 of the world and HUD pipelines.
+/// The real layouts are created from these descriptors, and the shaders are
+/// checked against them.
+const CAMERA_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+    label: Some("camera_layout"),
+    entries: UniformBindings::ENTRIES,
+};
+/// Layout of the model uniform, bound to group 1
+const MODEL_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+    label: Some("model_layout"),
+    entries: UniformBindings::ENTRIES,
+};
 
 pub struct Context<'a> {
     size: winit::dpi::PhysicalSize<u32>,
@@ -20,6 +49,14 @@ pub struct Context<'a> {
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
+    // What the adapter supports for the surface format, as far as the device allows
+    format_features: wgpu::TextureFormatFeatures,
+    // Sample counts the adapter supports for the surface format
+    supported_sample_counts: Vec<u32>,
+    sample_count: u32,
+    // Multisampled colour target, resolved into the surface. Only exists
+    // when sample_count is bigger than 1.
+    multisampled_view: Option<wgpu::TextureView>,
     // The window must be declared after the surface so
     // it gets dropped after it as the surface contains
     // unsafe references to the window's resources.
@@ -56,7 +93,9 @@ impl<'a> Context<'a> {
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: wgpu::Features::empty(),
+                    // Sample counts other than 1 and 4 depend on the adapter
+                    required_features: adapter.features()
+                        & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
                     // WebGL doesn't support all of wgpu's features, so if
                     // we're building for the web we'll have to disable some.
                     required_limits: if cfg!(target_arch = "wasm32") {
@@ -79,8 +118,14 @@ impl<'a> Context<'a> {
             .copied()
             .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
+        // Frame capture copies from the surface, when the platform allows it
+        let usage = if cfg!(not(target_arch = "wasm32")) {
+            surface_caps.usages & wgpu::TextureUsages::COPY_SRC
+        } else {
+            wgpu::TextureUsages::empty()
+        };
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | usage,
             format: surface_format,
             width: size.width,
             height: size.height,
@@ -90,11 +135,28 @@ impl<'a> Context<'a> {
             view_formats: vec![],
         };
 
+        let format_features = if device
+            .features()
+            .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+        {
+            adapter.get_texture_format_features(surface_format)
+        } else {
+            surface_format.guaranteed_format_features(device.features())
+        };
+        let supported_sample_counts = [1, 2, 4, 8]
+            .into_iter()
+            .filter(|count| format_features.flags.sample_count_supported(*count))
+            .collect();
+
         Self {
             surface,
             device,
             queue,
             config,
+            format_features,
+            supported_sample_counts,
+            sample_count: 1,
+            multisampled_view: None,
             size,
             window,
         }
@@ -110,91 +172,61 @@ impl<'a> Context<'a> {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            self.multisampled_view = self.create_multisampled_view();
         }
     }
 
-    pub fn get_size(&self) -> winit::dpi::PhysicalSize<u32> {
-        self.size
+    /// The highest supported sample count not above the requested one
+    fn closest_sample_count(&self, sample_count: u32) -> u32 {
+        self.supported_sample_counts
+            .iter()
+            .copied()
+            .filter(|count| *count <= sample_count)
+            .max()
+            .unwrap_or(1)
     }
 
-    pub fn get_queue(&self) -> &wgpu::Queue {
-        &self.queue
+    /// Sets the MSAA sample count, recreating the multisampled colour target
+    fn set_sample_count(&mut self, sample_count: u32) {
+        self.sample_count = self.closest_sample_count(sample_count);
+        self.multisampled_view = self.create_multisampled_view();
     }
 
-    pub fn get_device(&self) -> &wgpu::Device {
-        &self.device
+    fn create_multisampled_view(&self) -> Option<wgpu::TextureView> {
+        if self.sample_count == 1 {
+            return None;
+        }
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("multisampled_texture"),
+            size: wgpu::Extent3d {
+                width: self.config.width.max(1),
+                height: self.config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: self.sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
     }
-}
-
-/// A gadget to bind a Shader, VertexBuffer and Bindgroups into a RenderPipeline
-pub struct Gadget {
-    pipeline: wgpu::RenderPipeline,
-}
 
-impl Gadget {
-    pub fn from(
-        shader_src: wgpu::ShaderModuleDescriptor,
-        vertex_layout: wgpu::VertexBufferLayout,
-        uniforms: &[&wgpu::BindGroupLayout],
-        device: &wgpu::Device,
-        format: wgpu::TextureFormat,
-    ) -> Self {
-        let shader = device.create_shader_module(shader_src);
-
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: uniforms,
-                push_constant_ranges: &[],
-            });
+    pub fn supported_sample_counts(&self) -> &[u32] {
+        &self.supported_sample_counts
+    }
 
-        Self {
-            pipeline: device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    buffers: &[vertex_layout],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::LineStrip,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-                cache: None,
-            }),
-        }
+    pub fn get_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
     }
-}
 
-impl Deref for Gadget {
-    type Target = wgpu::RenderPipeline;
+    pub fn get_queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.pipeline
+    pub fn get_device(&self) -> &wgpu::Device {
+        &self.device
     }
 }
 
@@ -202,36 +234,56 @@ impl Deref for Gadget {
 /// enabling the rendering of Geometries. Effectively manages the
 /// rendering of Geometries with differing Gadgets (representing WebGPU
 /// pipelines).
+/// The world is seen through a camera of type C, the overlay is always orthographic.
 /// TODO: enable the usage of multiple Gadgets to render different Meshes
-pub struct Renderer<'a> {
-    camera: OrthoCamera,
+pub struct Renderer<'a, C: Camera = OrthoCamera> {
+    camera: C,
+    // Shake and zoom applied on top of the world camera's view
+    camera_effect: glam::Mat4,
+    #[cfg(not(target_arch = "wasm32"))]
+    capture: Capture,
+    // Screen space camera for the HUD and menus
+    hud_camera: OrthoCamera,
     gadget: Gadget,
+    // Same as gadget, for Geometries drawn as line lists
+    list_gadget: Gadget,
+    lines: LineRenderer,
+    particles: ParticleRenderer,
+    phosphor: Phosphor,
+    settings: RenderSettings,
+    shaders: Shaders,
+    // Line strips pushed for the current frame only
+    stream: LineStream,
+    #[cfg(feature = "hot-reload")]
+    last_shader_check: std::time::Instant,
     uniforms: HashMap<&'a str, UniformBinding>,
     context: Context<'a>,
 }
 
-impl<'a> Renderer<'a> {
-    pub async fn new(window: &'a Window) -> Renderer<'a> {
-        let context = Context::<'a>::new(window).await;
+impl<'a, C: Camera> Renderer<'a, C> {
+    pub async fn new(window: &'a Window, camera: C, settings: RenderSettings) -> Renderer<'a, C> {
+        let mut context = Context::<'a>::new(window).await;
+        context.set_sample_count(settings.sample_count);
         let mut uniforms: HashMap<&'a str, UniformBinding> = HashMap::new();
 
         // Create camera_bind_group_layout
-        let camera_binding = UniformBinding::new::<OrthoCamera>(&context.device);
-        let mut camera = OrthoCamera::new(context.size.width, context.size.height);
+        let camera_binding = UniformBinding::new(&context.device, CAMERA_LAYOUT);
+        let mut camera = camera;
         camera.setup(&context.device, &camera_binding);
+        camera.resize(context.size.width, context.size.height, &context.queue);
+        let mut hud_camera =
+            OrthoCamera::with_origin(context.size.width, context.size.height, Origin::TopLeft);
+        hud_camera.setup(&context.device, &camera_binding);
 
         // Create mesh_bind_group_layout
-        let model_matrix_binding = UniformBinding::new::<Geometry>(&context.device);
-
-        // Create a gadget for rendering with a camera and model matrix, using Vertex as
-        // the geometry buffer
-        let gadget = Gadget::from(
-            wgpu::include_wgsl!("shader.wgsl"),
-            Vertex::desc(),
-            &[&camera_binding, &model_matrix_binding],
-            &context.device,
-            context.config.format,
-        );
+        let model_matrix_binding = UniformBinding::new(&context.device, MODEL_LAYOUT);
+
+        let shaders = Shaders::default();
+        let (gadget, list_gadget, lines, particles, phosphor) =
+            create_pipelines(&context, &shaders, &camera_binding, &model_matrix_binding)
+                .expect("Couldn't create the render pipelines");
+
+        let stream = LineStream::new(&context.device, &model_matrix_binding);
 
         // Add UniformBindings to the uniform map
         uniforms.insert("camera", camera_binding);
@@ -239,7 +291,23 @@ impl<'a> Renderer<'a> {
 
         Self {
             camera,
+            camera_effect: glam::Mat4::IDENTITY,
+            #[cfg(not(target_arch = "wasm32"))]
+            capture: Capture::new(CAPTURE_EVERY),
+            hud_camera,
             gadget,
+            list_gadget,
+            lines,
+            particles,
+            phosphor,
+            settings: RenderSettings {
+                sample_count: context.sample_count,
+                ..settings
+            },
+            shaders,
+            stream,
+            #[cfg(feature = "hot-reload")]
+            last_shader_check: std::time::Instant::now(),
             uniforms,
             context,
         }
@@ -254,14 +322,120 @@ impl<'a> Renderer<'a> {
         }
     }
 
-    /// Renders meshes using the single Gadgets
+    /// Replaces the world camera, sizing it to the surface
+    pub fn set_camera(&mut self, mut camera: C) {
+        camera.setup(&self.context.device, self.get_uniform_binding("camera"));
+        camera.resize(self.size.width, self.size.height, &self.context.queue);
+        self.camera = camera;
+    }
+
+    /// Converts a position in window pixels to world coordinates, see Camera::window_to_world
+    pub fn window_to_world(&self, position: glam::Vec2) -> Option<glam::Vec2> {
+        self.camera.window_to_world(position)
+    }
+
+    /// Sets the shake and zoom to apply to the world camera, see CameraEffects.
+    /// Ignored with reduced motion.
+    pub fn set_camera_effect(&mut self, effect: glam::Mat4) {
+        self.camera_effect = effect;
+    }
+
+    /// The settings in use, with the sample count the adapter supports
+    pub fn settings(&self) -> RenderSettings {
+        self.settings
+    }
+
+    /// Applies new render settings. Changing the sample count recreates the
+    /// multisampled colour target and rebuilds the pipelines.
+    pub fn set_settings(&mut self, settings: RenderSettings) {
+        // Don't show stale trails from the last time the effect was on
+        if settings.phosphor.enabled && !self.settings.phosphor.enabled {
+            self.phosphor.clear();
+        }
+        let previous_sample_count = self.context.sample_count;
+        if self.context.closest_sample_count(settings.sample_count) != previous_sample_count {
+            self.context.set_sample_count(settings.sample_count);
+            match create_pipelines(
+                &self.context,
+                &self.shaders,
+                self.get_uniform_binding("camera"),
+                self.get_uniform_binding("model"),
+            ) {
+                Ok(pipelines) => {
+                    (
+                        self.gadget,
+                        self.list_gadget,
+                        self.lines,
+                        self.particles,
+                        self.phosphor,
+                    ) = pipelines
+                }
+                Err(error) => {
+                    log::error!("Keeping {previous_sample_count}x MSAA: {error}");
+                    self.context.set_sample_count(previous_sample_count);
+                }
+            }
+        }
+        self.settings = RenderSettings {
+            sample_count: self.context.sample_count,
+            line_width: settings.line_width.max(0.0),
+            ..settings
+        };
+    }
+
+    /// Adds world space line strips, each given by its range of vertices, to be
+    /// drawn by the next render only. For meshes that change every frame.
+    pub fn stream(&mut self, vertices: &[Vertex], strips: impl IntoIterator<Item = Range<u32>>) {
+        self.stream.push(vertices, strips);
+    }
+
+    /// Renders meshes using the single Gadgets, then the streamed strips and the
+    /// particles on top of them,
+    /// followed by the overlay meshes (HUD, menus) in screen space, with the origin
+    /// on the top left corner
     /// TODO: enable multiple Gadgets and different runs of render for the same pass
-    pub fn render(&mut self, meshes: &[Geometry]) -> Result<(), wgpu::SurfaceError> {
-        let output = self.context.surface.get_current_texture()?;
+    pub fn render(
+        &mut self,
+        meshes: &[Geometry],
+        particles: &ParticleSystem,
+        overlay: &[Geometry],
+    ) -> Result<(), wgpu::SurfaceError> {
+        #[cfg(feature = "hot-reload")]
+        self.reload_shaders();
+
+        let output = match self.context.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(error) => {
+                // Skipped frames don't carry their strips over to the next one
+                self.stream.clear();
+                return Err(error);
+            }
+        };
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
+        let draw_lines = self.settings.line_width > 0.0;
+        if draw_lines {
+            self.lines.update_buffer(
+                &self.context.queue,
+                self.context.size,
+                self.settings.line_width,
+                self.context.window.scale_factor(),
+            );
+        }
+
+        self.particles.update_buffer(&self.context.queue, particles);
+        self.stream
+            .upload(&self.context.device, &self.context.queue);
+        let camera_effect = if self.settings.reduced_motion {
+            glam::Mat4::IDENTITY
+        } else {
+            self.camera_effect
+        };
+        self.camera
+            .update_buffer_with(&self.context.queue, camera_effect);
+
         let mut encoder =
             self.context
                 .device
@@ -269,57 +443,440 @@ impl<'a> Renderer<'a> {
                     label: Some("Render Encoder"),
                 });
 
+        let phosphor = self.settings.phosphor.enabled;
+        let load = if phosphor {
+            self.phosphor.load_op()
+        } else {
+            wgpu::LoadOp::Clear(wgpu::Color {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+                a: 0.0,
+            })
+        };
+        // When multisampling, the multisampled target is resolved into the surface
+        // and also keeps the accumulated frames for phosphor persistence. Otherwise
+        // those accumulate in their own buffer, blitted into the surface afterwards.
+        let (target, resolve_target) = match &self.context.multisampled_view {
+            Some(multisampled_view) => (multisampled_view, Some(&view)),
+            None if phosphor => (self.phosphor.view(), None),
+            None => (&view, None),
+        };
+        let store = if phosphor || resolve_target.is_none() {
+            wgpu::StoreOp::Store
+        } else {
+            wgpu::StoreOp::Discard
+        };
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 0.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
+                    view: target,
+                    resolve_target,
+                    ops: wgpu::Operations { load, store },
                 })],
                 depth_stencil_attachment: None,
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
 
-            render_pass.set_pipeline(&self.gadget);
-            self.camera.bind_group(&mut render_pass);
-            meshes.iter().for_each(|mesh| {
-                mesh.render(&mut render_pass, 0..1);
-            });
+            if phosphor {
+                self.phosphor
+                    .fade(&mut render_pass, self.settings.phosphor.decay);
+            }
+            // Streamed strips and particles only exist in the world, under the overlay
+            let passes: [(&dyn Camera, _, _); 2] = [
+                (&self.camera, meshes, true),
+                (&self.hud_camera, overlay, false),
+            ];
+            for (camera, meshes, is_world) in passes {
+                if draw_lines {
+                    self.lines.bind(&mut render_pass);
+                }
+                camera.bind_group(&mut render_pass);
+                // Leave the bars around a fixed size playfield empty
+                match camera.letterbox() {
+                    Some(letterbox) => render_pass.set_scissor_rect(
+                        letterbox.x,
+                        letterbox.y,
+                        letterbox.width,
+                        letterbox.height,
+                    ),
+                    None => render_pass.set_scissor_rect(
+                        0,
+                        0,
+                        self.context.config.width,
+                        self.context.config.height,
+                    ),
+                }
+                meshes.iter().for_each(|mesh| {
+                    if draw_lines {
+                        mesh.render_lines(&mut render_pass);
+                    } else {
+                        render_pass.set_pipeline(match mesh.primitive() {
+                            Primitive::LineStrip => &self.gadget,
+                            Primitive::LineList => &self.list_gadget,
+                        });
+                        mesh.render(&mut render_pass, 0..1);
+                    }
+                });
+                if is_world {
+                    if draw_lines {
+                        self.stream.render_lines(&mut render_pass);
+                    } else {
+                        render_pass.set_pipeline(&self.gadget);
+                        self.stream.render(&mut render_pass);
+                    }
+                    self.particles.render(&mut render_pass);
+                }
+            }
+        }
+        if phosphor && self.context.multisampled_view.is_none() {
+            self.phosphor.blit(&mut encoder, &view);
         }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.capture
+            .copy(&self.context.device, &mut encoder, &output.texture);
         self.context.queue.submit(iter::once(encoder.finish()));
         output.present();
+        self.stream.clear();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.capture.poll(&self.context.device);
 
         Ok(())
     }
 
-    #[allow(unused_variables)]
+    /// Rebuilds the Gadgets whose shaders changed on disk. If the new source or
+    /// pipeline fails validation the error is logged and the previous shader and
+    /// Gadget kept. Shader files are polled every CHECK_INTERVAL rather than watched,
+    /// a few metadata calls per check being negligible next to a frame.
+    #[cfg(feature = "hot-reload")]
+    fn reload_shaders(&mut self) {
+        const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+        if self.last_shader_check.elapsed() < CHECK_INTERVAL {
+            return;
+        }
+        self.last_shader_check = std::time::Instant::now();
+
+        let (context, camera_layout, model_layout) = (
+            &self.context,
+            &self.uniforms["camera"],
+            &self.uniforms["model"],
+        );
+        // A shader replaces the previous one only once its Gadgets are valid, so
+        // later rebuilds, like changing the sample count, can't pick up a bad source
+        if let Some(shader) = self.shaders.default.reload(&self.shaders.preprocessor) {
+            if let Some((gadget, list_gadget)) = catch_validation(&context.device, || {
+                let create = |topology| {
+                    create_gadget(context, &shader, camera_layout, model_layout, topology)
+                };
+                Ok((
+                    create(wgpu::PrimitiveTopology::LineStrip)?,
+                    create(wgpu::PrimitiveTopology::LineList)?,
+                ))
+            }) {
+                (self.gadget, self.list_gadget) = (gadget, list_gadget);
+                self.shaders.default = shader;
+                log::info!("Reloaded {}", self.shaders.default.label());
+            }
+        }
+        if let Some(shader) = self.shaders.lines.reload(&self.shaders.preprocessor) {
+            if let Some(lines) = catch_validation(&context.device, || {
+                create_lines(context, &shader, camera_layout, model_layout)
+            }) {
+                self.lines = lines;
+                self.shaders.lines = shader;
+                log::info!("Reloaded {}", self.shaders.lines.label());
+            }
+        }
+        if let Some(shader) = self.shaders.particles.reload(&self.shaders.preprocessor) {
+            if let Some(particles) = catch_validation(&context.device, || {
+                create_particles(context, &shader, camera_layout)
+            }) {
+                self.particles = particles;
+                self.shaders.particles = shader;
+                log::info!("Reloaded {}", self.shaders.particles.label());
+            }
+        }
+        if let Some(shader) = self.shaders.phosphor.reload(&self.shaders.preprocessor) {
+            if let Some(phosphor) =
+                catch_validation(&context.device, || create_phosphor(context, &shader))
+            {
+                self.phosphor = phosphor;
+                self.shaders.phosphor = shader;
+                log::info!("Reloaded {}", self.shaders.phosphor.label());
+            }
+        }
+    }
+
+    /// Handles renderer specific input, returning true if the event was consumed.
+    /// P toggles the phosphor persistence effect, [ and ] change the line width
+    /// and M cycles through the supported MSAA sample counts. F1 toggles reduced
+    /// motion, disabling screen shake. On native, F12 saves
+    /// a screenshot, F10 and F9 start or stop recording a PNG sequence or a GIF.
     pub fn input(&mut self, event: &WindowEvent) -> bool {
-        false
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: PhysicalKey::Code(key),
+                    repeat,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return false;
+        };
+        let mut settings = self.settings();
+        match key {
+            KeyCode::KeyP if !repeat => settings.phosphor.enabled = !settings.phosphor.enabled,
+            KeyCode::KeyM if !repeat => {
+                let counts = self.context.supported_sample_counts();
+                settings.sample_count = counts
+                    .iter()
+                    .copied()
+                    .find(|count| *count > settings.sample_count)
+                    .unwrap_or(counts[0]);
+                log::info!("MSAA sample count: {}", settings.sample_count);
+            }
+            KeyCode::F1 if !repeat => {
+                settings.reduced_motion = !settings.reduced_motion;
+                log::info!("Reduced motion: {}", settings.reduced_motion);
+            }
+            KeyCode::BracketLeft => settings.line_width -= 0.5,
+            KeyCode::BracketRight => settings.line_width += 0.5,
+            #[cfg(not(target_arch = "wasm32"))]
+            KeyCode::F12 if !repeat => {
+                self.capture.request_screenshot();
+                return true;
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            KeyCode::F10 if !repeat => {
+                self.capture.toggle_recording(RecordingFormat::PngSequence);
+                return true;
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            KeyCode::F9 if !repeat => {
+                self.capture.toggle_recording(RecordingFormat::Gif);
+                return true;
+            }
+            _ => return false,
+        }
+        self.set_settings(settings);
+        true
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.context.resize(new_size);
+            self.phosphor.resize(&self.context.device, new_size);
             self.camera
                 .resize(new_size.width, new_size.height, &self.context.queue);
+            self.hud_camera
+                .resize(new_size.width, new_size.height, &self.context.queue);
         }
     }
 }
 
-impl<'a> Deref for Renderer<'a> {
+#[cfg(not(target_arch = "wasm32"))]
+impl<C: Camera> Drop for Renderer<'_, C> {
+    /// Sends the frames still being captured to the encoding thread, which the
+    /// Capture then waits for
+    fn drop(&mut self) {
+        self.capture.finish(&self.context.device);
+    }
+}
+
+impl<'a, C: Camera> Deref for Renderer<'a, C> {
     type Target = Context<'a>;
 
     fn deref(&self) -> &Self::Target {
         &self.context
     }
 }
+
+/// Recordings capture one of every CAPTURE_EVERY frames
+#[cfg(not(target_arch = "wasm32"))]
+const CAPTURE_EVERY: u32 = 2;
+
+/// Creates the default Gadgets for line strips and lists, the line and particle
+/// renderers and the phosphor effect, for the current surface format and sample count
+fn create_pipelines(
+    context: &Context,
+    shaders: &Shaders,
+    camera_layout: &UniformBinding,
+    model_layout: &UniformBinding,
+) -> Result<(Gadget, Gadget, LineRenderer, ParticleRenderer, Phosphor), GadgetError> {
+    let gadget = create_gadget(
+        context,
+        &shaders.default,
+        camera_layout,
+        model_layout,
+        wgpu::PrimitiveTopology::LineStrip,
+    )?;
+    let list_gadget = create_gadget(
+        context,
+        &shaders.default,
+        camera_layout,
+        model_layout,
+        wgpu::PrimitiveTopology::LineList,
+    )?;
+    let lines = create_lines(context, &shaders.lines, camera_layout, model_layout)?;
+    let particles = create_particles(context, &shaders.particles, camera_layout)?;
+    let phosphor = create_phosphor(context, &shaders.phosphor)?;
+    Ok((gadget, list_gadget, lines, particles, phosphor))
+}
+
+/// Creates a gadget for rendering with a camera and model matrix, using Vertex as
+/// the geometry buffer
+fn create_gadget(
+    context: &Context,
+    shader: &Shader,
+    camera_layout: &UniformBinding,
+    model_layout: &UniformBinding,
+    topology: wgpu::PrimitiveTopology,
+) -> Result<Gadget, GadgetError> {
+    check_gadget(shader, camera_layout.desc(), model_layout.desc())?;
+    GadgetBuilder::new(&shader.module(&context.device), context.config.format)
+        .label("Render Pipeline")
+        .format_features(context.format_features)
+        .vertex_layout(Vertex::desc())
+        .bind_group_layouts(&[camera_layout, model_layout])
+        .topology(topology)
+        .sample_count(context.sample_count)
+        .build(&context.device, &shader.reflect()?)
+}
+
+fn create_lines(
+    context: &Context,
+    shader: &Shader,
+    camera_layout: &UniformBinding,
+    model_layout: &UniformBinding,
+) -> Result<LineRenderer, GadgetError> {
+    check_lines(shader, camera_layout.desc(), model_layout.desc())?;
+    LineRenderer::new(
+        &context.device,
+        shader,
+        camera_layout,
+        model_layout,
+        context.config.format,
+        context.format_features,
+        context.sample_count,
+    )
+}
+
+fn create_particles(
+    context: &Context,
+    shader: &Shader,
+    camera_layout: &UniformBinding,
+) -> Result<ParticleRenderer, GadgetError> {
+    check_particles(shader, camera_layout.desc())?;
+    ParticleRenderer::new(
+        &context.device,
+        shader,
+        camera_layout,
+        context.config.format,
+        context.format_features,
+        context.sample_count,
+    )
+}
+
+fn create_phosphor(context: &Context, shader: &Shader) -> Result<Phosphor, GadgetError> {
+    check_phosphor(shader)?;
+    Phosphor::new(
+        &context.device,
+        shader,
+        context.config.format,
+        context.format_features,
+        context.size,
+        context.sample_count,
+    )
+}
+
+/// Checks the default shader against the layouts of the Gadgets in create_gadget
+fn check_gadget(
+    shader: &Shader,
+    camera: &wgpu::BindGroupLayoutDescriptor,
+    model: &wgpu::BindGroupLayoutDescriptor,
+) -> Result<(), ReflectionError> {
+    shader.check_layouts(&[Vertex::desc()], &[camera.clone(), model.clone()])
+}
+
+/// Checks the line shader against the layouts of the LineRenderer
+fn check_lines(
+    shader: &Shader,
+    camera: &wgpu::BindGroupLayoutDescriptor,
+    model: &wgpu::BindGroupLayoutDescriptor,
+) -> Result<(), ReflectionError> {
+    shader.check_layouts(
+        &segment_layouts(),
+        &[camera.clone(), model.clone(), LINE_LAYOUT],
+    )
+}
+
+/// Checks the particle shader against the layouts of the ParticleRenderer
+fn check_particles(
+    shader: &Shader,
+    camera: &wgpu::BindGroupLayoutDescriptor,
+) -> Result<(), ReflectionError> {
+    shader.check_layouts(&[ParticleInstance::desc()], std::slice::from_ref(camera))
+}
+
+/// Checks the phosphor shader against the layouts of its fade and blit pipelines
+fn check_phosphor(shader: &Shader) -> Result<(), ReflectionError> {
+    shader.check_entry_points("vs_fullscreen", "fs_fade", &[], &[])?;
+    shader.check_entry_points("vs_fullscreen", "fs_blit", &[], &[PHOSPHOR_LAYOUT])
+}
+
+/// Runs create inside a validation error scope, so an invalid Gadget is logged
+/// instead of panicking, and the previous one can be kept
+#[cfg(feature = "hot-reload")]
+fn catch_validation<T>(
+    device: &wgpu::Device,
+    create: impl FnOnce() -> Result<T, GadgetError>,
+) -> Option<T> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let result = create();
+    let scope_error = pollster::block_on(device.pop_error_scope());
+    match (result, scope_error) {
+        (Ok(value), None) => Some(value),
+        (Err(error), _) => {
+            log::error!("{error}");
+            None
+        }
+        (_, Some(error)) => {
+            log::error!("{error}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shaders_match_pipeline_layouts() {
+        let shaders = Shaders::default();
+        assert_eq!(
+            check_gadget(&shaders.default, &CAMERA_LAYOUT, &MODEL_LAYOUT),
+            Ok(())
+        );
+        assert_eq!(
+            check_lines(&shaders.lines, &CAMERA_LAYOUT, &MODEL_LAYOUT),
+            Ok(())
+        );
+        assert_eq!(check_particles(&shaders.particles, &CAMERA_LAYOUT), Ok(()));
+        assert_eq!(check_phosphor(&shaders.phosphor), Ok(()));
+    }
+
+    #[test]
+    fn mismatched_layouts() {
+        let shaders = Shaders::default();
+        assert!(check_particles(&shaders.particles, &PHOSPHOR_LAYOUT).is_err());
+        assert!(check_gadget(&shaders.lines, &CAMERA_LAYOUT, &MODEL_LAYOUT).is_err());
+        assert!(check_phosphor(&shaders.default).is_err());
+    }
+}
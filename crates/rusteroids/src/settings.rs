@@ -0,0 +1,26 @@
+use crate::lines::DEFAULT_LINE_WIDTH;
+use crate::phosphor::PhosphorSettings;
+
+/// Rendering settings that can be changed at runtime through Renderer::set_settings
+#[derive(Clone, Copy, Debug)]
+pub struct RenderSettings {
+    /// MSAA sample count (1, 2, 4 or 8). Falls back to the highest count the
+    /// adapter supports for the surface format.
+    pub sample_count: u32,
+    /// Line width in logical pixels, zero falls back to hairlines
+    pub line_width: f32,
+    pub phosphor: PhosphorSettings,
+    /// Disables screen shake and other camera effects
+    pub reduced_motion: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            sample_count: 4,
+            line_width: DEFAULT_LINE_WIDTH,
+            phosphor: PhosphorSettings::default(),
+            reduced_motion: false,
+        }
+    }
+}
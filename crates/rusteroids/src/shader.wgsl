@@ -1,11 +1,7 @@
 // Vertex shader
-struct CameraUniform {
-    view_proj: mat4x4<f32>,
-};
-
-struct ModelUniform {
-    matrix: mat4x4<f32>,
-};
+#include "CameraUniform"
+#include "ModelUniform"
+#include "Vertex"
 
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
@@ -13,11 +9,6 @@ var<uniform> camera: CameraUniform;
 @group(1) @binding(0)
 var<uniform> xform: ModelUniform;
 
-struct VertexInput {
-    @location(0) position: vec2<f32>,
-    @location(1) color: vec3<f32>,
-};
-
 struct VertexOutput{
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
@@ -25,7 +16,7 @@ struct VertexOutput{
 
 @vertex
 fn vs_main(
-    model: VertexInput,
+    model: Vertex,
 ) -> VertexOutput {
     var out: VertexOutput;
     out.clip_position = camera.view_proj * xform.matrix * vec4<f32>(model.position,0.0,1.0);
@@ -0,0 +1,176 @@
+use std::borrow::Cow;
+
+use wgpu_utils::reflect::{ReflectionError, ShaderReflection};
+use wgpu_utils::wgsl::WgslPreprocessor;
+
+use crate::camera::CameraUniform;
+use crate::lines::LineUniform;
+use crate::mesh::{ModelUniform, Vertex};
+use crate::particles::ParticleInstance;
+
+/// A WGSL shader embedded in the binary. With the hot-reload feature, its source
+/// is loaded from the crate's src folder instead, and reloaded whenever the file
+/// changes and the new source passes naga validation. Changes are found by polling
+/// the file's modification time from the render loop, see Renderer::reload_shaders,
+/// which is cheap enough for a development feature and needs no watcher thread.
+/// Sources are preprocessed, so they can #include the declarations generated from
+/// Rust types.
+pub struct Shader {
+    label: &'static str,
+    source: String,
+    #[cfg(feature = "hot-reload")]
+    modified: Option<std::time::SystemTime>,
+}
+
+impl Shader {
+    /// Creates a shader from its file name in src and its embedded source
+    pub fn new(
+        label: &'static str,
+        embedded: &'static str,
+        preprocessor: &WgslPreprocessor,
+    ) -> Self {
+        let source = preprocessor
+            .process(embedded)
+            .unwrap_or_else(|error| panic!("Invalid {label}: {error}"));
+        #[allow(unused_mut)]
+        let mut shader = Self {
+            label,
+            source,
+            #[cfg(feature = "hot-reload")]
+            modified: None,
+        };
+        #[cfg(feature = "hot-reload")]
+        if let Some(reloaded) = shader.reload(preprocessor) {
+            shader = reloaded;
+        }
+        shader
+    }
+
+    #[cfg(feature = "hot-reload")]
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+
+    pub fn module(&self, device: &wgpu::Device) -> wgpu::ShaderModule {
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(self.label),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(&self.source)),
+        })
+    }
+
+    /// Parses the source with naga, to check the pipelines created from the shader
+    pub fn reflect(&self) -> Result<ShaderReflection, ReflectionError> {
+        ShaderReflection::parse(&self.source)
+    }
+
+    /// Checks the vertex buffers and bind groups of a pipeline with the vs_main and
+    /// fs_main entry points against what the shader declares
+    pub fn check_layouts(
+        &self,
+        vertex_layouts: &[wgpu::VertexBufferLayout],
+        bind_groups: &[wgpu::BindGroupLayoutDescriptor],
+    ) -> Result<(), ReflectionError> {
+        self.check_entry_points("vs_main", "fs_main", vertex_layouts, bind_groups)
+    }
+
+    /// Checks the vertex buffers and bind groups of a pipeline with the given entry
+    /// points against what the shader declares
+    pub fn check_entry_points(
+        &self,
+        vertex: &str,
+        fragment: &str,
+        vertex_layouts: &[wgpu::VertexBufferLayout],
+        bind_groups: &[wgpu::BindGroupLayoutDescriptor],
+    ) -> Result<(), ReflectionError> {
+        let reflection = self.reflect()?;
+        reflection.check_vertex_layouts(vertex, vertex_layouts)?;
+        reflection.check_bind_groups(&[vertex, fragment], bind_groups)
+    }
+
+    /// Loads the source again if the file changed since the last time it was checked.
+    /// Returns the shader with the new source when it passes naga validation, otherwise
+    /// logs why it was rejected. This shader keeps its source either way, so it can
+    /// still be used if the pipelines built from the new one turn out to be invalid.
+    #[cfg(feature = "hot-reload")]
+    pub fn reload(&mut self, preprocessor: &WgslPreprocessor) -> Option<Shader> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src")
+            .join(self.label);
+        let modified = match std::fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(error) => {
+                log::error!("Couldn't watch {}: {error}", path.display());
+                return None;
+            }
+        };
+        if self.modified == Some(modified) {
+            return None;
+        }
+        self.modified = Some(modified);
+
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) => {
+                log::error!("Couldn't read {}: {error}", path.display());
+                return None;
+            }
+        };
+        let source = match preprocessor.process(&source) {
+            Ok(source) => source,
+            Err(error) => {
+                log::error!("Keeping the previous {}: {error}", self.label);
+                return None;
+            }
+        };
+        if source == self.source {
+            return None;
+        }
+        if let Err(error) = ShaderReflection::parse(&source) {
+            log::error!("Keeping the previous {}:\n{error}", self.label);
+            return None;
+        }
+        Some(Shader {
+            label: self.label,
+            source,
+            modified: self.modified,
+        })
+    }
+}
+
+/// The shaders used by the Renderer's Gadgets
+pub struct Shaders {
+    pub default: Shader,
+    pub lines: Shader,
+    pub particles: Shader,
+    pub phosphor: Shader,
+    // Kept to preprocess the sources again when they're reloaded
+    #[cfg(feature = "hot-reload")]
+    pub preprocessor: WgslPreprocessor,
+}
+
+impl Default for Shaders {
+    fn default() -> Self {
+        let preprocessor = WgslPreprocessor::new()
+            .with_struct::<CameraUniform>()
+            .with_struct::<ModelUniform>()
+            .with_struct::<LineUniform>()
+            .with_struct::<Vertex>()
+            .with_struct::<ParticleInstance>();
+        Self {
+            default: Shader::new("shader.wgsl", include_str!("shader.wgsl"), &preprocessor),
+            lines: Shader::new("lines.wgsl", include_str!("lines.wgsl"), &preprocessor),
+            particles: Shader::new(
+                "particles.wgsl",
+                include_str!("particles.wgsl"),
+                &preprocessor,
+            ),
+            phosphor: Shader::new(
+                "phosphor.wgsl",
+                include_str!("phosphor.wgsl"),
+                &preprocessor,
+            ),
+            #[cfg(feature = "hot-reload")]
+            preprocessor,
+        }
+    }
+}
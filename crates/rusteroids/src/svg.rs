@@ -0,0 +1,871 @@
+use std::f32::consts::TAU;
+use std::fmt;
+
+use glam::{vec2, Affine2, Vec2};
+
+use crate::mesh::Vertex;
+
+/// Segments each Bézier curve is flattened into
+const CURVE_SEGMENTS: u32 = 8;
+/// Arcs get a segment for each of these angles they sweep
+const ARC_STEP: f32 = TAU / 32.0;
+/// Outlines without a stroke colour, SVG would hide them but we rarely want that
+const DEFAULT_STROKE: [f32; 3] = [1.0, 1.0, 1.0];
+
+/// Errors found while loading an SVG outline
+#[derive(Clone, Debug, PartialEq)]
+pub enum SvgError {
+    Xml(roxmltree::Error),
+    /// Byte offset into the path data where parsing failed
+    InvalidPath(usize),
+    InvalidPoints(String),
+    InvalidTransform(String),
+    InvalidColor(String),
+    Empty,
+    TooManyVertices(usize),
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgError::Xml(error) => write!(f, "invalid SVG document: {error}"),
+            SvgError::InvalidPath(offset) => {
+                write!(f, "path data is malformed at byte {offset}")
+            }
+            SvgError::InvalidPoints(points) => write!(f, "invalid points list \"{points}\""),
+            SvgError::InvalidTransform(transform) => {
+                write!(f, "invalid transform \"{transform}\"")
+            }
+            SvgError::InvalidColor(color) => write!(f, "unsupported colour \"{color}\""),
+            SvgError::Empty => write!(f, "the document has no stroked outlines"),
+            SvgError::TooManyVertices(count) => {
+                write!(f, "{count} vertices don't fit 16 bit indices")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SvgError {}
+
+impl From<roxmltree::Error> for SvgError {
+    fn from(error: roxmltree::Error) -> Self {
+        SvgError::Xml(error)
+    }
+}
+
+/// Line list mesh loaded from the `<path>`, `<polyline>` and `<polygon>` elements
+/// of an SVG document, so outlines can be drawn in vector tools
+pub struct SvgMesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+}
+
+impl SvgMesh {
+    /// Parses the outlines of an SVG document, centered on the origin and scaled
+    /// so their largest side is unit_size. The y axis is flipped, so the top of
+    /// the drawing points up in the world.
+    /// Transforms and stroke colours are inherited from groups, curves and arcs
+    /// are flattened, and fills, styles other than stroke and the viewBox are ignored.
+    pub fn parse(source: &str, unit_size: f32) -> Result<Self, SvgError> {
+        let document = roxmltree::Document::parse(source)?;
+        let mut outlines = Vec::new();
+        collect(
+            document.root_element(),
+            Affine2::IDENTITY,
+            Some(DEFAULT_STROKE),
+            &mut outlines,
+        )?;
+
+        let (min, max) = outlines
+            .iter()
+            .flat_map(|outline| &outline.points)
+            .fold((Vec2::INFINITY, Vec2::NEG_INFINITY), |(min, max), point| {
+                (min.min(*point), max.max(*point))
+            });
+        if min.x > max.x {
+            return Err(SvgError::Empty);
+        }
+        let center = (min + max) / 2.0;
+        let extent = (max - min).max_element();
+        let scale = if extent > 0.0 {
+            unit_size / extent
+        } else {
+            1.0
+        };
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for outline in &outlines {
+            let first = vertices.len();
+            let count = outline.points.len();
+            if first + count > u16::MAX as usize + 1 {
+                return Err(SvgError::TooManyVertices(first + count));
+            }
+            vertices.extend(outline.points.iter().map(|point| Vertex {
+                position: (*point - center) * vec2(scale, -scale),
+                color: outline.color,
+            }));
+            let segments = if outline.closed { count } else { count - 1 };
+            indices.extend(
+                (0..segments).flat_map(|i| [first + i, first + (i + 1) % count].map(|i| i as u16)),
+            );
+        }
+        Ok(Self { vertices, indices })
+    }
+}
+
+/// A polyline in document coordinates
+struct Outline {
+    points: Vec<Vec2>,
+    closed: bool,
+    color: [f32; 3],
+}
+
+/// Walks the element tree, accumulating transforms and the inherited stroke,
+/// where None means the stroke was disabled
+fn collect(
+    node: roxmltree::Node,
+    parent_transform: Affine2,
+    parent_stroke: Option<[f32; 3]>,
+    outlines: &mut Vec<Outline>,
+) -> Result<(), SvgError> {
+    let name = node.tag_name().name();
+    // Only referenced from elsewhere, never drawn as is
+    if matches!(name, "defs" | "clipPath" | "mask" | "symbol" | "marker") {
+        return Ok(());
+    }
+    if node.attribute("display") == Some("none") {
+        return Ok(());
+    }
+    let transform = match node.attribute("transform") {
+        Some(transform) => parent_transform * parse_transform(transform)?,
+        None => parent_transform,
+    };
+    let stroke = match style_property(node, "stroke") {
+        Some("none") => None,
+        Some("inherit") | None => parent_stroke,
+        Some(color) => Some(parse_color(color)?),
+    };
+
+    let polylines = match name {
+        "path" => parse_path(node.attribute("d").unwrap_or_default())?,
+        "polyline" | "polygon" => {
+            let points = node.attribute("points").unwrap_or_default();
+            vec![(parse_points(points)?, name == "polygon")]
+        }
+        _ => Vec::new(),
+    };
+    if let Some(color) = stroke {
+        outlines.extend(
+            polylines
+                .into_iter()
+                .filter(|(points, _)| points.len() > 1)
+                .map(|(points, closed)| Outline {
+                    // Closing a single segment would draw it twice
+                    closed: closed && points.len() > 2,
+                    points: points
+                        .into_iter()
+                        .map(|point| transform.transform_point2(point))
+                        .collect(),
+                    color,
+                }),
+        );
+    }
+
+    for child in node.children().filter(|child| child.is_element()) {
+        collect(child, transform, stroke, outlines)?;
+    }
+    Ok(())
+}
+
+/// A presentation attribute, overridden by the same property in the style attribute
+fn style_property<'a>(node: roxmltree::Node<'a, '_>, property: &str) -> Option<&'a str> {
+    let from_style = node.attribute("style").and_then(|style| {
+        style.split(';').find_map(|declaration| {
+            let (key, value) = declaration.split_once(':')?;
+            (key.trim() == property).then(|| value.trim())
+        })
+    });
+    from_style.or_else(|| node.attribute(property).map(str::trim))
+}
+
+/// Flattens path data into polylines, flagged when closed
+fn parse_path(data: &str) -> Result<Vec<(Vec<Vec2>, bool)>, SvgError> {
+    let mut tokens = Tokens::new(data);
+    let mut polylines = Vec::new();
+    let mut line = Vec::new();
+    let (mut current, mut start) = (Vec2::ZERO, Vec2::ZERO);
+    let mut previous = None;
+    // Control points the smooth curve commands reflect, when following a curve
+    let (mut cubic_control, mut quad_control): (Option<Vec2>, Option<Vec2>) = (None, None);
+    while !tokens.at_end() {
+        let offset = tokens.pos;
+        // Coordinates after the first pair of a move are implicit line commands
+        let command = match (tokens.command(), previous) {
+            (Some(command), _) => command,
+            (None, Some(b'M')) => b'L',
+            (None, Some(b'm')) => b'l',
+            (None, Some(b'Z' | b'z') | None) => return Err(SvgError::InvalidPath(offset)),
+            (None, Some(command)) => command,
+        };
+        previous = Some(command);
+        let origin = if command.is_ascii_lowercase() {
+            current
+        } else {
+            Vec2::ZERO
+        };
+        let (mut next_cubic, mut next_quad) = (None, None);
+        match command.to_ascii_uppercase() {
+            b'M' => {
+                flush(&mut polylines, &mut line, false);
+                current = origin + tokens.point()?;
+                start = current;
+                line.push(current);
+            }
+            b'L' => {
+                let end = origin + tokens.point()?;
+                line_to(&mut line, current, end);
+                current = end;
+            }
+            b'H' => {
+                let end = vec2(origin.x + tokens.number()?, current.y);
+                line_to(&mut line, current, end);
+                current = end;
+            }
+            b'V' => {
+                let end = vec2(current.x, origin.y + tokens.number()?);
+                line_to(&mut line, current, end);
+                current = end;
+            }
+            b'C' | b'S' => {
+                let first = if command.eq_ignore_ascii_case(&b'C') {
+                    origin + tokens.point()?
+                } else {
+                    cubic_control.map_or(current, |control| current * 2.0 - control)
+                };
+                let second = origin + tokens.point()?;
+                let end = origin + tokens.point()?;
+                for i in 1..=CURVE_SEGMENTS {
+                    let t = i as f32 / CURVE_SEGMENTS as f32;
+                    let s = 1.0 - t;
+                    let point = current * s * s * s
+                        + first * 3.0 * s * s * t
+                        + second * 3.0 * s * t * t
+                        + end * t * t * t;
+                    line_to(&mut line, current, point);
+                }
+                next_cubic = Some(second);
+                current = end;
+            }
+            b'Q' | b'T' => {
+                let control = if command.eq_ignore_ascii_case(&b'Q') {
+                    origin + tokens.point()?
+                } else {
+                    quad_control.map_or(current, |control| current * 2.0 - control)
+                };
+                let end = origin + tokens.point()?;
+                for i in 1..=CURVE_SEGMENTS {
+                    let t = i as f32 / CURVE_SEGMENTS as f32;
+                    let s = 1.0 - t;
+                    let point = current * s * s + control * 2.0 * s * t + end * t * t;
+                    line_to(&mut line, current, point);
+                }
+                next_quad = Some(control);
+                current = end;
+            }
+            b'A' => {
+                let radii = vec2(tokens.number()?, tokens.number()?).abs();
+                let rotation = tokens.number()?.to_radians();
+                let (large_arc, sweep) = (tokens.flag()?, tokens.flag()?);
+                let end = origin + tokens.point()?;
+                arc_to(&mut line, current, end, radii, rotation, large_arc, sweep);
+                current = end;
+            }
+            b'Z' => {
+                flush(&mut polylines, &mut line, true);
+                current = start;
+            }
+            _ => return Err(SvgError::InvalidPath(offset)),
+        }
+        cubic_control = next_cubic;
+        quad_control = next_quad;
+    }
+    flush(&mut polylines, &mut line, false);
+    Ok(polylines)
+}
+
+fn line_to(line: &mut Vec<Vec2>, current: Vec2, point: Vec2) {
+    // Drawing after closing a subpath continues from its start
+    if line.is_empty() {
+        line.push(current);
+    }
+    if line.last() != Some(&point) {
+        line.push(point);
+    }
+}
+
+fn flush(polylines: &mut Vec<(Vec<Vec2>, bool)>, line: &mut Vec<Vec2>, closed: bool) {
+    let mut points = std::mem::take(line);
+    if closed && points.len() > 2 && points.first() == points.last() {
+        points.pop();
+    }
+    if points.len() > 1 {
+        polylines.push((points, closed));
+    }
+}
+
+/// Flattens an elliptical arc, converting its endpoints to a center and angles
+/// as in the SVG implementation notes
+fn arc_to(
+    line: &mut Vec<Vec2>,
+    current: Vec2,
+    end: Vec2,
+    mut radii: Vec2,
+    rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+) {
+    if current == end {
+        return;
+    }
+    if radii.x == 0.0 || radii.y == 0.0 {
+        line_to(line, current, end);
+        return;
+    }
+    let axes = Vec2::from_angle(rotation);
+    let half = Vec2::from_angle(-rotation).rotate((current - end) / 2.0);
+    // Radii too small to reach the end are scaled up until they just do
+    let lambda = (half / radii).length_squared();
+    if lambda > 1.0 {
+        radii *= lambda.sqrt();
+    }
+    let (rx2, ry2) = (radii.x * radii.x, radii.y * radii.y);
+    let (hx2, hy2) = (half.x * half.x, half.y * half.y);
+    let sign = if large_arc == sweep { -1.0 } else { 1.0 };
+    let coefficient = sign
+        * ((rx2 * ry2 - rx2 * hy2 - ry2 * hx2) / (rx2 * hy2 + ry2 * hx2))
+            .max(0.0)
+            .sqrt();
+    let center_prime = coefficient * vec2(radii.x * half.y / radii.y, -radii.y * half.x / radii.x);
+    let center = axes.rotate(center_prime) + (current + end) / 2.0;
+
+    let from = (half - center_prime) / radii;
+    let to = (-half - center_prime) / radii;
+    let start_angle = Vec2::X.angle_to(from);
+    let mut sweep_angle = from.angle_to(to);
+    if sweep && sweep_angle < 0.0 {
+        sweep_angle += TAU;
+    } else if !sweep && sweep_angle > 0.0 {
+        sweep_angle -= TAU;
+    }
+    let segments = (sweep_angle.abs() / ARC_STEP).ceil().max(1.0) as u32;
+    for i in 1..segments {
+        let angle = start_angle + sweep_angle * i as f32 / segments as f32;
+        let point = center + axes.rotate(Vec2::from_angle(angle) * radii);
+        line_to(line, current, point);
+    }
+    // Lands exactly on the end, so following commands join up
+    line_to(line, current, end);
+}
+
+fn parse_points(points: &str) -> Result<Vec<Vec2>, SvgError> {
+    match parse_numbers(points) {
+        Some(numbers) if numbers.len() % 2 == 0 => Ok(numbers
+            .chunks_exact(2)
+            .map(|pair| vec2(pair[0], pair[1]))
+            .collect()),
+        _ => Err(SvgError::InvalidPoints(points.to_string())),
+    }
+}
+
+/// Parses a transform list, the rightmost transform is applied first
+fn parse_transform(list: &str) -> Result<Affine2, SvgError> {
+    let invalid = || SvgError::InvalidTransform(list.to_string());
+    let mut transform = Affine2::IDENTITY;
+    let mut rest = list.trim();
+    while !rest.is_empty() {
+        let (name, tail) = rest.split_once('(').ok_or_else(invalid)?;
+        let (arguments, tail) = tail.split_once(')').ok_or_else(invalid)?;
+        let arguments = parse_numbers(arguments).ok_or_else(invalid)?;
+        transform *= match (name.trim(), arguments.as_slice()) {
+            ("matrix", &[a, b, c, d, e, f]) => Affine2::from_cols_array(&[a, b, c, d, e, f]),
+            ("translate", &[x]) => Affine2::from_translation(vec2(x, 0.0)),
+            ("translate", &[x, y]) => Affine2::from_translation(vec2(x, y)),
+            ("scale", &[s]) => Affine2::from_scale(Vec2::splat(s)),
+            ("scale", &[x, y]) => Affine2::from_scale(vec2(x, y)),
+            ("rotate", &[angle]) => Affine2::from_angle(angle.to_radians()),
+            ("rotate", &[angle, x, y]) => {
+                Affine2::from_translation(vec2(x, y))
+                    * Affine2::from_angle(angle.to_radians())
+                    * Affine2::from_translation(vec2(-x, -y))
+            }
+            ("skewX", &[angle]) => {
+                Affine2::from_cols_array(&[1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0])
+            }
+            ("skewY", &[angle]) => {
+                Affine2::from_cols_array(&[1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0])
+            }
+            _ => return Err(invalid()),
+        };
+        rest = tail.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    }
+    Ok(transform)
+}
+
+/// Parses hex, rgb() and a handful of named colours
+fn parse_color(color: &str) -> Result<[f32; 3], SvgError> {
+    let invalid = || SvgError::InvalidColor(color.to_string());
+    if let Some(hex) = color.strip_prefix('#') {
+        let digits = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|digit| digit as u8))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        let rgb = match *digits.as_slice() {
+            [r, g, b] => [r * 17, g * 17, b * 17],
+            [r1, r2, g1, g2, b1, b2] => [r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2],
+            _ => return Err(invalid()),
+        };
+        return Ok(rgb.map(|channel| channel as f32 / 255.0));
+    }
+    if let Some(arguments) = color
+        .strip_prefix("rgb(")
+        .and_then(|arguments| arguments.strip_suffix(')'))
+    {
+        let channels = arguments
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|channel| !channel.is_empty())
+            .map(|channel| match channel.strip_suffix('%') {
+                Some(percent) => percent.parse::<f32>().map(|value| value / 100.0),
+                None => channel.parse::<f32>().map(|value| value / 255.0),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        return match *channels.as_slice() {
+            [r, g, b] => Ok([r, g, b].map(|channel| channel.clamp(0.0, 1.0))),
+            _ => Err(invalid()),
+        };
+    }
+    let rgb: [u8; 3] = match color.to_ascii_lowercase().as_str() {
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "lime" => [0, 255, 0],
+        "green" => [0, 128, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" | "aqua" => [0, 255, 255],
+        "magenta" | "fuchsia" => [255, 0, 255],
+        "orange" => [255, 165, 0],
+        "silver" => [192, 192, 192],
+        "gray" | "grey" => [128, 128, 128],
+        _ => return Err(invalid()),
+    };
+    Ok(rgb.map(|channel| channel as f32 / 255.0))
+}
+
+/// Numbers separated by whitespace and/or commas, None if any is malformed
+fn parse_numbers(list: &str) -> Option<Vec<f32>> {
+    let mut tokens = Tokens::new(list);
+    let mut numbers = Vec::new();
+    while !tokens.at_end() {
+        numbers.push(tokens.number().ok()?);
+    }
+    Some(numbers)
+}
+
+/// Tokenizer for SVG number lists and path data, where separators are optional
+/// whenever the next token can't be confused with the previous one, as in "M10-5.5.5"
+struct Tokens<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(data: &'a str) -> Self {
+        Self {
+            data: data.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|c| c.is_ascii_whitespace() || *c == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.pos >= self.data.len()
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let command = *self
+            .data
+            .get(self.pos)
+            .filter(|c| c.is_ascii_alphabetic())?;
+        self.pos += 1;
+        Some(command)
+    }
+
+    fn number(&mut self) -> Result<f32, SvgError> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |tokens: &mut Self| {
+            let from = tokens.pos;
+            while tokens.data.get(tokens.pos).is_some_and(u8::is_ascii_digit) {
+                tokens.pos += 1;
+            }
+            tokens.pos > from
+        };
+        if matches!(self.data.get(self.pos), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut has_digits = digits(self);
+        if self.data.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            has_digits |= digits(self);
+        }
+        if !has_digits {
+            return Err(SvgError::InvalidPath(start));
+        }
+        // Only an exponent if digits follow, "e" alone isn't part of a number
+        if matches!(self.data.get(self.pos), Some(b'e' | b'E')) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if matches!(self.data.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                self.pos = mantissa_end;
+            }
+        }
+        std::str::from_utf8(&self.data[start..self.pos])
+            .ok()
+            .and_then(|number| number.parse().ok())
+            .ok_or(SvgError::InvalidPath(start))
+    }
+
+    fn point(&mut self) -> Result<Vec2, SvgError> {
+        Ok(vec2(self.number()?, self.number()?))
+    }
+
+    /// Arc flags are a single digit and may be followed directly by a number
+    fn flag(&mut self) -> Result<bool, SvgError> {
+        self.skip_separators();
+        let flag = match self.data.get(self.pos) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(SvgError::InvalidPath(self.pos)),
+        };
+        self.pos += 1;
+        Ok(flag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_points_eq(a: &[Vec2], b: &[Vec2]) {
+        assert_eq!(a.len(), b.len(), "{a:?} != {b:?}");
+        for (a, b) in a.iter().zip(b) {
+            assert!(a.abs_diff_eq(*b, 1e-4), "{a} != {b}");
+        }
+    }
+
+    fn single_polyline(data: &str) -> (Vec<Vec2>, bool) {
+        let mut polylines = parse_path(data).unwrap();
+        assert_eq!(polylines.len(), 1, "{polylines:?}");
+        polylines.remove(0)
+    }
+
+    #[test]
+    fn tokenizer_without_separators() {
+        let mut tokens = Tokens::new("M10-5.5.5");
+        assert_eq!(tokens.command(), Some(b'M'));
+        assert_eq!(tokens.number(), Ok(10.0));
+        assert_eq!(tokens.number(), Ok(-5.5));
+        assert_eq!(tokens.number(), Ok(0.5));
+        assert!(tokens.at_end());
+    }
+
+    #[test]
+    fn tokenizer_exponents() {
+        assert_eq!(
+            parse_numbers("1e2,-2.5E-1 +3e+1"),
+            Some(vec![100.0, -0.25, 30.0])
+        );
+        // Without digits, "e" is the next command
+        let mut tokens = Tokens::new("3e");
+        assert_eq!(tokens.number(), Ok(3.0));
+        assert_eq!(tokens.command(), Some(b'e'));
+        assert_eq!(parse_numbers("1 e"), None);
+    }
+
+    #[test]
+    fn tokenizer_arc_flags() {
+        // Flags run into each other and into the following number
+        let mut tokens = Tokens::new("5 5 0 1150 0");
+        assert_eq!(tokens.point(), Ok(vec2(5.0, 5.0)));
+        assert_eq!(tokens.number(), Ok(0.0));
+        assert_eq!(tokens.flag(), Ok(true));
+        assert_eq!(tokens.flag(), Ok(true));
+        assert_eq!(tokens.point(), Ok(vec2(50.0, 0.0)));
+        assert!(tokens.at_end());
+        assert_eq!(Tokens::new(" 2").flag(), Err(SvgError::InvalidPath(1)));
+    }
+
+    #[test]
+    fn implicit_line_commands() {
+        let (points, closed) = single_polyline("M0 0 10 0 10 10");
+        assert_points_eq(
+            &points,
+            &[vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(10.0, 10.0)],
+        );
+        assert!(!closed);
+
+        let (points, closed) = single_polyline("m1 1 2 0 0 2z");
+        assert_points_eq(&points, &[vec2(1.0, 1.0), vec2(3.0, 1.0), vec2(3.0, 3.0)]);
+        assert!(closed);
+
+        // Other commands repeat themselves
+        let (points, _) = single_polyline("M0 0 L1 0 1 1 h-1 -1");
+        assert_points_eq(
+            &points,
+            &[
+                vec2(0.0, 0.0),
+                vec2(1.0, 0.0),
+                vec2(1.0, 1.0),
+                vec2(0.0, 1.0),
+                vec2(-1.0, 1.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn subpaths() {
+        let polylines = parse_path("M0 0 L1 0 L1 1 Z L0 1 M5 5 L6 6").unwrap();
+        assert_eq!(polylines.len(), 3);
+        assert!(polylines[0].1);
+        // Drawing after closing continues from the start of the subpath
+        assert_points_eq(&polylines[1].0, &[vec2(0.0, 0.0), vec2(0.0, 1.0)]);
+        assert_points_eq(&polylines[2].0, &[vec2(5.0, 5.0), vec2(6.0, 6.0)]);
+    }
+
+    #[test]
+    fn smooth_cubic_reflects_control_point() {
+        let smooth = single_polyline("M0 0 C0 10 10 10 10 0 S20 -10 20 0").0;
+        let explicit = single_polyline("M0 0 C0 10 10 10 10 0 C10 -10 20 -10 20 0").0;
+        assert_points_eq(&smooth, &explicit);
+        assert_eq!(smooth.len(), 1 + 2 * CURVE_SEGMENTS as usize);
+
+        // Without a previous curve the first control point is the current point
+        let smooth = single_polyline("M0 0 L5 0 S10 10 10 0").0;
+        let explicit = single_polyline("M0 0 L5 0 C5 0 10 10 10 0").0;
+        assert_points_eq(&smooth, &explicit);
+    }
+
+    #[test]
+    fn smooth_quadratic_reflects_control_point() {
+        let smooth = single_polyline("M0 0 Q5 10 10 0 t10 0").0;
+        let explicit = single_polyline("M0 0 Q5 10 10 0 Q15 -10 20 0").0;
+        assert_points_eq(&smooth, &explicit);
+
+        // A cubic's control point isn't reflected by T
+        let smooth = single_polyline("M0 0 C0 10 10 10 10 0 T20 0").0;
+        let explicit = single_polyline("M0 0 C0 10 10 10 10 0 Q10 0 20 0").0;
+        assert_points_eq(&smooth, &explicit);
+    }
+
+    #[test]
+    fn arc_center() {
+        // Half a circle around (10, 0), sweeping through negative y
+        let (points, _) = single_polyline("M0 0 A10 10 0 0 1 20 0");
+        assert_eq!(points.len(), 17);
+        assert_eq!(points.last(), Some(&vec2(20.0, 0.0)));
+        for point in &points {
+            assert!((point.distance(vec2(10.0, 0.0)) - 10.0).abs() < 1e-3);
+        }
+        assert!(points[8].abs_diff_eq(vec2(10.0, -10.0), 1e-3));
+        let (points, _) = single_polyline("M0 0 A10 10 0 0 0 20 0");
+        assert!(points[8].abs_diff_eq(vec2(10.0, 10.0), 1e-3));
+
+        // Radii too small to reach the end are scaled up to the same circle
+        let (scaled, _) = single_polyline("M0 0 A1 1 0 0 1 20 0");
+        assert_points_eq(&scaled, &single_polyline("M0 0 A10 10 0 0 1 20 0").0);
+    }
+
+    #[test]
+    fn arc_flags_pick_the_center() {
+        let height = |data| {
+            single_polyline(data)
+                .0
+                .iter()
+                .map(|point| point.y.abs())
+                .fold(0.0, f32::max)
+        };
+        // The chord is 20 long, so the centers are sqrt(20² - 10²) away from it
+        let offset = 300.0_f32.sqrt();
+        assert!(height("M0 0 A20 20 0 0 1 20 0") <= 20.0 - offset + 1e-3);
+        assert!((height("M0 0 A20 20 0 1 1 20 0") - (20.0 + offset)).abs() < 0.5);
+
+        // Rotated ellipses still land on the end
+        let (points, _) = single_polyline("M0 0 A20 10 30 1 0 20 5");
+        assert_eq!(points.last(), Some(&vec2(20.0, 5.0)));
+        // Zero radii draw a line
+        let (points, _) = single_polyline("M0 0 A0 10 0 0 1 20 0");
+        assert_points_eq(&points, &[vec2(0.0, 0.0), vec2(20.0, 0.0)]);
+    }
+
+    #[test]
+    fn transform_order() {
+        // The rightmost transform applies first
+        let transform = parse_transform("translate(10 0) scale(2)").unwrap();
+        assert_eq!(transform.transform_point2(vec2(1.0, 1.0)), vec2(12.0, 2.0));
+        let transform = parse_transform("scale(2),translate(10)").unwrap();
+        assert_eq!(transform.transform_point2(vec2(1.0, 1.0)), vec2(22.0, 2.0));
+
+        let transform = parse_transform("rotate(90 5 5)").unwrap();
+        assert!(transform
+            .transform_point2(vec2(10.0, 5.0))
+            .abs_diff_eq(vec2(5.0, 10.0), 1e-4));
+        let transform = parse_transform(" matrix(1 0 0 1 3 4) skewX(45) ").unwrap();
+        assert!(transform
+            .transform_point2(vec2(0.0, 1.0))
+            .abs_diff_eq(vec2(4.0, 5.0), 1e-4));
+    }
+
+    #[test]
+    fn inherited_transforms_and_strokes() {
+        let mesh = SvgMesh::parse(
+            r##"<svg xmlns="http://www.w3.org/2000/svg">
+                <g transform="translate(10 0)" stroke="red">
+                    <polyline points="0,0 0,10" transform="scale(2)" />
+                    <polyline points="0,0 5,0" stroke="#00f" />
+                    <polyline points="0,0 9,9" style="stroke: none" />
+                </g>
+                <polyline points="0,0 1,1" stroke="lime" display="none" />
+            </svg>"##,
+            20.0,
+        )
+        .unwrap();
+        // Document points (10, 0) (10, 20) (10, 0) (15, 0), centered on (12.5, 10)
+        assert_eq!(
+            mesh.vertices
+                .iter()
+                .map(|vertex| (vertex.position, vertex.color))
+                .collect::<Vec<_>>(),
+            [
+                (vec2(-2.5, 10.0), [1.0, 0.0, 0.0]),
+                (vec2(-2.5, -10.0), [1.0, 0.0, 0.0]),
+                (vec2(-2.5, 10.0), [0.0, 0.0, 1.0]),
+                (vec2(2.5, 10.0), [0.0, 0.0, 1.0]),
+            ]
+        );
+        assert_eq!(mesh.indices, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#fff"), Ok([1.0; 3]));
+        assert_eq!(parse_color("#FF8000"), Ok([1.0, 128.0 / 255.0, 0.0]));
+        assert_eq!(parse_color("rgb(255, 0, 50%)"), Ok([1.0, 0.0, 0.5]));
+        assert_eq!(parse_color("rgb(300 0 0)"), Ok([1.0, 0.0, 0.0]));
+        assert_eq!(parse_color("Orange"), Ok([1.0, 165.0 / 255.0, 0.0]));
+        for invalid in [
+            "#12",
+            "#ggg",
+            "rgb(1, 2)",
+            "hsl(0, 0%, 0%)",
+            "rebeccapurple",
+        ] {
+            assert_eq!(
+                parse_color(invalid),
+                Err(SvgError::InvalidColor(invalid.to_string()))
+            );
+        }
+    }
+
+    fn parse_error(document: &str) -> SvgError {
+        SvgMesh::parse(document, 1.0).err().unwrap()
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(parse_error("<svg"), SvgError::Xml(_)));
+        assert_eq!(
+            parse_error(r#"<svg><path d="M0 0 L1" /></svg>"#),
+            SvgError::InvalidPath(7)
+        );
+        assert_eq!(
+            parse_error(r#"<svg><path d="10 10" /></svg>"#),
+            SvgError::InvalidPath(0)
+        );
+        assert_eq!(
+            parse_error(r#"<svg><path d="M0 0 X1 1" /></svg>"#),
+            SvgError::InvalidPath(5)
+        );
+        assert_eq!(
+            parse_error(r#"<svg><polygon points="0,0 1" /></svg>"#),
+            SvgError::InvalidPoints("0,0 1".to_string())
+        );
+        assert_eq!(
+            parse_error(r#"<svg transform="translate(1 2 3)"><path d="M0 0 1 1" /></svg>"#),
+            SvgError::InvalidTransform("translate(1 2 3)".to_string())
+        );
+        assert_eq!(
+            parse_error(r#"<svg><path d="M0 0 1 1" stroke="hsl(0 0 0)" /></svg>"#),
+            SvgError::InvalidColor("hsl(0 0 0)".to_string())
+        );
+        assert_eq!(parse_error("<svg />"), SvgError::Empty);
+        assert_eq!(
+            parse_error(r#"<svg stroke="none"><path d="M0 0 1 1" /></svg>"#),
+            SvgError::Empty
+        );
+        assert_eq!(
+            SvgError::TooManyVertices(70000).to_string(),
+            "70000 vertices don't fit 16 bit indices"
+        );
+    }
+
+    #[test]
+    fn too_many_vertices() {
+        let polyline = |count: usize| {
+            let points: String = (0..count).map(|i| format!("{i},0 ")).collect();
+            format!(r#"<svg><polyline points="{points}" /></svg>"#)
+        };
+        let mesh = SvgMesh::parse(&polyline(u16::MAX as usize + 1), 1.0).unwrap();
+        assert_eq!(mesh.indices.last(), Some(&u16::MAX));
+        assert_eq!(
+            parse_error(&polyline(u16::MAX as usize + 2)),
+            SvgError::TooManyVertices(u16::MAX as usize + 2)
+        );
+    }
+
+    #[test]
+    fn ship_asset() {
+        let ship = SvgMesh::parse(include_str!("../assets/ship.svg"), 40.0).unwrap();
+        // The nose points up, the outline is closed
+        assert_eq!(
+            ship.vertices
+                .iter()
+                .map(|vertex| vertex.position)
+                .collect::<Vec<_>>(),
+            [
+                vec2(0.0, 20.0),
+                vec2(10.0, -20.0),
+                vec2(0.0, -10.0),
+                vec2(-10.0, -20.0),
+            ]
+        );
+        assert!(ship.vertices.iter().all(|vertex| vertex.color == [1.0; 3]));
+        assert_eq!(ship.indices, [0, 1, 1, 2, 2, 3, 3, 0]);
+    }
+}
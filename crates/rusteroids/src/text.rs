@@ -0,0 +1,278 @@
+use std::ops::Range;
+
+use glam::{vec2, Vec2};
+
+use crate::mesh::{Geometry, Vertex};
+
+/// Glyphs are drawn on a grid GLYPH_WIDTH units wide and GLYPH_HEIGHT units tall
+const GLYPH_WIDTH: f32 = 4.0;
+const GLYPH_HEIGHT: f32 = 6.0;
+/// Horizontal distance between the start of two consecutive glyphs
+const ADVANCE: f32 = 6.0;
+/// Vertical distance between the top of two consecutive lines
+const LINE_HEIGHT: f32 = 10.0;
+
+/// A glyph is a list of strokes, each a polyline over the glyph grid, y pointing up
+type Glyph = &'static [&'static [(i8, i8)]];
+
+/// Horizontal alignment of each line of text relative to its position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextStyle {
+    pub align: TextAlign,
+    /// Size of a glyph grid unit in pixels, glyphs are 6 units tall
+    pub scale: f32,
+    pub color: [f32; 3],
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            align: TextAlign::Left,
+            scale: 2.0,
+            color: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// Lays out a string with the stroke font in screen space, x pointing right and
+/// y pointing down. The position is the top of the first line, on the left, center
+/// or right of each line according to alignment. Returns the vertices and the range
+/// of each stroke, to be drawn as separate line strips.
+pub fn layout(text: &str, position: Vec2, style: &TextStyle) -> (Vec<Vertex>, Vec<Range<u32>>) {
+    let mut vertices = Vec::new();
+    let mut strokes = Vec::new();
+    for (row, line) in text.lines().enumerate() {
+        let width = line_width(line) * style.scale;
+        let start_x = match style.align {
+            TextAlign::Left => position.x,
+            TextAlign::Center => position.x - width / 2.0,
+            TextAlign::Right => position.x - width,
+        };
+        let top = position.y + row as f32 * LINE_HEIGHT * style.scale;
+        for (column, c) in line.chars().enumerate() {
+            let left = start_x + column as f32 * ADVANCE * style.scale;
+            for stroke in glyph(c).iter() {
+                let start = vertices.len() as u32;
+                vertices.extend(stroke.iter().map(|&(x, y)| Vertex {
+                    position: vec2(
+                        left + x as f32 * style.scale,
+                        top + (GLYPH_HEIGHT - y as f32) * style.scale,
+                    ),
+                    color: style.color,
+                }));
+                strokes.push(start..vertices.len() as u32);
+            }
+        }
+    }
+    (vertices, strokes)
+}
+
+/// Creates a Geometry with the text laid out by layout
+pub fn geometry(
+    text: &str,
+    position: Vec2,
+    style: &TextStyle,
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    mesh_index: u32,
+) -> Geometry {
+    let (vertices, strokes) = layout(text, position, style);
+    Geometry::with_strips(&vertices, strokes, device, bind_group_layout, mesh_index)
+}
+
+/// Replaces the text of a Geometry created by geometry, reusing its buffers
+pub fn update_geometry(
+    geometry: &mut Geometry,
+    text: &str,
+    position: Vec2,
+    style: &TextStyle,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) {
+    let (vertices, strokes) = layout(text, position, style);
+    geometry.update_strips(device, queue, &vertices, strokes);
+}
+
+/// Width of a single line of text in glyph grid units
+fn line_width(line: &str) -> f32 {
+    match line.chars().count() {
+        0 => 0.0,
+        count => (count - 1) as f32 * ADVANCE + GLYPH_WIDTH,
+    }
+}
+
+/// Returns the strokes of a character. Lowercase letters use the uppercase glyphs
+/// and unsupported characters are left blank, still taking up their advance.
+fn glyph(c: char) -> Glyph {
+    match c.to_ascii_uppercase() {
+        ' ' => &[],
+        'A' => &[&[(0, 0), (0, 4), (2, 6), (4, 4), (4, 0)], &[(0, 2), (4, 2)]],
+        'B' => &[
+            &[(0, 0), (0, 6), (3, 6), (4, 5), (4, 4), (3, 3), (0, 3)],
+            &[(3, 3), (4, 2), (4, 1), (3, 0), (0, 0)],
+        ],
+        'C' => &[&[(4, 0), (0, 0), (0, 6), (4, 6)]],
+        'D' => &[&[(0, 0), (0, 6), (2, 6), (4, 4), (4, 2), (2, 0), (0, 0)]],
+        'E' => &[&[(4, 0), (0, 0), (0, 6), (4, 6)], &[(0, 3), (3, 3)]],
+        'F' => &[&[(0, 0), (0, 6), (4, 6)], &[(0, 3), (3, 3)]],
+        'G' => &[&[(2, 2), (4, 2), (4, 0), (0, 0), (0, 6), (4, 6), (4, 4)]],
+        'H' => &[&[(0, 0), (0, 6)], &[(4, 0), (4, 6)], &[(0, 3), (4, 3)]],
+        'I' => &[&[(0, 0), (4, 0)], &[(2, 0), (2, 6)], &[(0, 6), (4, 6)]],
+        'J' => &[&[(0, 2), (2, 0), (4, 0), (4, 6)]],
+        'K' => &[&[(0, 0), (0, 6)], &[(4, 6), (0, 3), (4, 0)]],
+        'L' => &[&[(0, 6), (0, 0), (4, 0)]],
+        'M' => &[&[(0, 0), (0, 6), (2, 4), (4, 6), (4, 0)]],
+        'N' => &[&[(0, 0), (0, 6), (4, 0), (4, 6)]],
+        'O' => &[&[(0, 0), (0, 6), (4, 6), (4, 0), (0, 0)]],
+        'P' => &[&[(0, 0), (0, 6), (4, 6), (4, 3), (0, 3)]],
+        'Q' => &[
+            &[(0, 0), (0, 6), (4, 6), (4, 2), (2, 0), (0, 0)],
+            &[(2, 2), (4, 0)],
+        ],
+        'R' => &[&[(0, 0), (0, 6), (4, 6), (4, 3), (0, 3)], &[(1, 3), (4, 0)]],
+        'S' => &[&[(0, 0), (4, 0), (4, 3), (0, 3), (0, 6), (4, 6)]],
+        'T' => &[&[(0, 6), (4, 6)], &[(2, 6), (2, 0)]],
+        'U' => &[&[(0, 6), (0, 0), (4, 0), (4, 6)]],
+        'V' => &[&[(0, 6), (2, 0), (4, 6)]],
+        'W' => &[&[(0, 6), (0, 0), (2, 2), (4, 0), (4, 6)]],
+        'X' => &[&[(0, 0), (4, 6)], &[(0, 6), (4, 0)]],
+        'Y' => &[&[(0, 6), (2, 4), (4, 6)], &[(2, 4), (2, 0)]],
+        'Z' => &[&[(0, 6), (4, 6), (0, 0), (4, 0)]],
+        '0' => &[&[(0, 0), (0, 6), (4, 6), (4, 0), (0, 0)], &[(0, 0), (4, 6)]],
+        '1' => &[&[(1, 5), (2, 6), (2, 0)]],
+        '2' => &[&[(0, 6), (4, 6), (4, 3), (0, 3), (0, 0), (4, 0)]],
+        '3' => &[&[(0, 6), (4, 6), (4, 0), (0, 0)], &[(0, 3), (4, 3)]],
+        '4' => &[&[(0, 6), (0, 3), (4, 3)], &[(4, 6), (4, 0)]],
+        '5' => &[&[(4, 6), (0, 6), (0, 3), (4, 3), (4, 0), (0, 0)]],
+        '6' => &[&[(0, 6), (0, 0), (4, 0), (4, 3), (0, 3)]],
+        '7' => &[&[(0, 6), (4, 6), (4, 0)]],
+        '8' => &[&[(0, 0), (4, 0), (4, 6), (0, 6), (0, 0)], &[(0, 3), (4, 3)]],
+        '9' => &[&[(4, 3), (0, 3), (0, 6), (4, 6), (4, 0)]],
+        '.' => &[&[(2, 0), (2, 1)]],
+        ',' => &[&[(2, 1), (1, -1)]],
+        ':' => &[&[(2, 1), (2, 2)], &[(2, 4), (2, 5)]],
+        '!' => &[&[(2, 6), (2, 2)], &[(2, 0), (2, 1)]],
+        '?' => &[
+            &[(0, 5), (1, 6), (3, 6), (4, 5), (4, 4), (2, 3), (2, 2)],
+            &[(2, 0), (2, 1)],
+        ],
+        '\'' => &[&[(2, 6), (2, 4)]],
+        '-' => &[&[(1, 3), (3, 3)]],
+        '+' => &[&[(0, 3), (4, 3)], &[(2, 1), (2, 5)]],
+        '=' => &[&[(0, 2), (4, 2)], &[(0, 4), (4, 4)]],
+        '/' => &[&[(0, 0), (4, 6)]],
+        '_' => &[&[(0, 0), (4, 0)]],
+        '[' => &[&[(3, 6), (1, 6), (1, 0), (3, 0)]],
+        ']' => &[&[(1, 6), (3, 6), (3, 0), (1, 0)]],
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(align: TextAlign) -> TextStyle {
+        TextStyle {
+            align,
+            scale: 2.0,
+            color: [0.5, 0.25, 1.0],
+        }
+    }
+
+    fn min_x(vertices: &[Vertex]) -> f32 {
+        vertices
+            .iter()
+            .map(|vertex| vertex.position.x)
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn line_widths() {
+        assert_eq!(line_width(""), 0.0);
+        assert_eq!(line_width("I"), GLYPH_WIDTH);
+        // Advances between glyphs, then the width of the last one
+        assert_eq!(line_width("SCORE"), 4.0 * ADVANCE + GLYPH_WIDTH);
+
+        let (vertices, _) = layout("SCORE", Vec2::ZERO, &style(TextAlign::Left));
+        let max_x = vertices
+            .iter()
+            .map(|vertex| vertex.position.x)
+            .fold(f32::NEG_INFINITY, f32::max);
+        assert_eq!(max_x, line_width("SCORE") * 2.0);
+    }
+
+    #[test]
+    fn alignment() {
+        // L starts on the left edge of its glyph, the width of IL is 10 units
+        let position = vec2(100.0, 50.0);
+        let left = layout("IL", position, &style(TextAlign::Left)).0;
+        let center = layout("IL", position, &style(TextAlign::Center)).0;
+        let right = layout("IL", position, &style(TextAlign::Right)).0;
+        assert_eq!(min_x(&left), 100.0);
+        assert_eq!(min_x(&center), 90.0);
+        assert_eq!(min_x(&right), 80.0);
+
+        // Every line is aligned on its own, the second one starting below the first
+        let (vertices, _) = layout("IL\nI", position, &style(TextAlign::Right));
+        let second: Vec<_> = vertices
+            .into_iter()
+            .filter(|vertex| vertex.position.y > 50.0 + GLYPH_HEIGHT * 2.0)
+            .collect();
+        assert_eq!(min_x(&second), 100.0 - GLYPH_WIDTH * 2.0);
+        assert!(second
+            .iter()
+            .all(|vertex| vertex.position.y >= 50.0 + LINE_HEIGHT * 2.0));
+    }
+
+    #[test]
+    fn lowercase_uses_uppercase_glyphs() {
+        let text_style = style(TextAlign::Left);
+        let (lower, lower_strokes) = layout("game over", Vec2::ZERO, &text_style);
+        let (upper, upper_strokes) = layout("GAME OVER", Vec2::ZERO, &text_style);
+        assert_eq!(lower_strokes, upper_strokes);
+        let positions = |vertices: &[Vertex]| -> Vec<Vec2> {
+            vertices.iter().map(|vertex| vertex.position).collect()
+        };
+        assert_eq!(positions(&lower), positions(&upper));
+    }
+
+    #[test]
+    fn unknown_characters_advance() {
+        let text_style = style(TextAlign::Left);
+        let (vertices, strokes) = layout("L~L", Vec2::ZERO, &text_style);
+        let (single, single_strokes) = layout("L", Vec2::ZERO, &text_style);
+        assert_eq!(strokes.len(), 2 * single_strokes.len());
+
+        // The second L is drawn two advances after the first
+        let offset = 2.0 * ADVANCE * text_style.scale;
+        let second = &vertices[single.len()..];
+        for (vertex, expected) in second.iter().zip(&single) {
+            assert_eq!(vertex.position, expected.position + vec2(offset, 0.0));
+        }
+    }
+
+    #[test]
+    fn colour_and_strokes() {
+        let text_style = style(TextAlign::Left);
+        let (vertices, strokes) = layout("AH", Vec2::ZERO, &text_style);
+        assert!(vertices
+            .iter()
+            .all(|vertex| vertex.color == text_style.color));
+
+        // Each stroke is a strip over the vertices following the previous one
+        assert_eq!(strokes.len(), glyph('A').len() + glyph('H').len());
+        assert_eq!(strokes[0], 0..5);
+        assert_eq!(strokes.last().unwrap().end, vertices.len() as u32);
+        for pair in strokes.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+    }
+}
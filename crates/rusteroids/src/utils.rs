@@ -1,50 +1,16 @@
+use std::marker::PhantomData;
 use std::ops::Deref;
 
 use wgpu::util::DeviceExt;
+use wgpu_utils::{Bindable, UniformLayout};
 
-use crate::mesh::Vertex;
-
-/// A bunch of boilerplate code and meshes for now
-
-/// The battleship mesh
-pub const WEDGE: &[Vertex] = &[
-    Vertex {
-        position: [0.0, 20.0],
-        color: [1.0, 1.0, 1.0],
-    },
-    Vertex {
-        position: [10.0, -20.0],
-        color: [1.0, 1.0, 1.0],
-    },
-    Vertex {
-        position: [0.0, -10.0],
-        color: [1.0, 1.0, 1.0],
-    },
-    Vertex {
-        position: [-10.0, -20.0],
-        color: [1.0, 1.0, 1.0],
-    },
-    Vertex {
-        position: [0.0, 20.0],
-        color: [1.0, 1.0, 1.0],
-    },
-];
-
-/// A common uniform layout descriptor, visible in both Vertex and Fragment
-pub fn common_layout_descriptor(label: Option<&str>) -> wgpu::BindGroupLayoutDescriptor {
-    wgpu::BindGroupLayoutDescriptor {
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
-        label,
-    }
+// A bunch of boilerplate code for now
+
+/// The bind group of a single uniform buffer, visible in both Vertex and Fragment
+#[derive(Bindable)]
+pub struct UniformBindings<'a> {
+    #[uniform]
+    pub uniform: &'a wgpu::Buffer,
 }
 
 // Create a buffer to be used as a uniform with a bind group
@@ -59,55 +25,41 @@ where
     })
 }
 
-pub fn create_bind_group(
-    device: &wgpu::Device,
-    layout: &wgpu::BindGroupLayout,
-    buffer: &wgpu::Buffer,
-    label: &str,
-) -> wgpu::BindGroup {
-    device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout,
-        entries: &[wgpu::BindGroupEntry {
-            binding: 0,
-            resource: buffer.as_entire_binding(),
-        }],
-        label: Some(label),
-    })
-}
-
 /// A struct to manage both a uniform buffer and its bind group in WebGPU.
 /// Directly relates to UniformBinding as the Layout needs to be managed outside of it.
-pub struct UniformBuffer {
+/// Only holds values of T, whose layout is checked against the WGSL rules for
+/// uniforms when compiling.
+pub struct UniformBuffer<T: UniformLayout + bytemuck::Pod> {
     buffer: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
+    _uniform: PhantomData<T>,
 }
 
-impl UniformBuffer {
+impl<T: UniformLayout + bytemuck::Pod> UniformBuffer<T> {
     /// Creates a new UniformBuffer struct. Device and Layout already needs to exist.
-    pub fn new<T>(
+    pub fn new(
         data: &T,
         device: &wgpu::Device,
         layout: &wgpu::BindGroupLayout,
         label_prefix: &str,
-    ) -> Self
-    where
-        T: bytemuck::Pod + bytemuck::Zeroable,
-    {
+    ) -> Self {
+        // Fails the build for generic uniforms with a layout WGSL reads differently
+        let () = T::CHECK;
         let buffer = create_buffer(data, device, &format!("{}_buffer", label_prefix));
-        let bind_group = create_bind_group(
+        let bind_group = UniformBindings { uniform: &buffer }.create_bind_group(
             device,
             layout,
-            &buffer,
-            &format!("{}_bind_group", label_prefix),
+            Some(&format!("{}_bind_group", label_prefix)),
         );
-        Self { buffer, bind_group }
+        Self {
+            buffer,
+            bind_group,
+            _uniform: PhantomData,
+        }
     }
 
     /// Updates the buffer content through a pre existing Queue
-    pub fn update_buffer<T>(&self, data: &T, queue: &wgpu::Queue)
-    where
-        T: bytemuck::Pod + bytemuck::Zeroable,
-    {
+    pub fn update_buffer(&self, data: &T, queue: &wgpu::Queue) {
         queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(data));
     }
 
@@ -117,22 +69,26 @@ impl UniformBuffer {
     }
 }
 
-/// Struct to manage the binding of Uniforms to a RenderPipeline according to Bindable type.
-/// Only enables the creation of the RenderPipeline with a specific BindGroup Layout. Buffer
-/// is managed via UniformBuffer.
+/// Struct to manage the binding of Uniforms to a RenderPipeline according to a layout
+/// descriptor, usually made of a Bindable type's entries. Only enables the creation of
+/// the RenderPipeline with a specific BindGroup Layout. Buffer is managed via UniformBuffer.
 pub struct UniformBinding {
     layout: wgpu::BindGroupLayout,
+    desc: wgpu::BindGroupLayoutDescriptor<'static>,
 }
 
 impl UniformBinding {
-    pub fn new<T>(device: &wgpu::Device) -> Self
-    where
-        T: Bindable,
-    {
+    pub fn new(device: &wgpu::Device, desc: wgpu::BindGroupLayoutDescriptor<'static>) -> Self {
         Self {
-            layout: device.create_bind_group_layout(&T::layout_desc()),
+            layout: device.create_bind_group_layout(&desc),
+            desc,
         }
     }
+
+    /// The descriptor the layout was created from, to check shaders against
+    pub fn desc(&self) -> &wgpu::BindGroupLayoutDescriptor<'static> {
+        &self.desc
+    }
 }
 
 impl Deref for UniformBinding {
@@ -143,8 +99,34 @@ impl Deref for UniformBinding {
     }
 }
 
-/// Marks a struct as Bindable to a pipeline, requirement the specification
-/// of a BindGroupLayoutDescriptor
-pub trait Bindable {
-    fn layout_desc<'a>() -> wgpu::BindGroupLayoutDescriptor<'a>;
+/// A small xorshift pseudo random number generator. Good enough for effects,
+/// and reproducible from its seed.
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        // Xorshift gets stuck on zero
+        Self {
+            state: if seed == 0 { 0x9e37_79b9 } else { seed },
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+
+    /// A float in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// A float in [min, max)
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
 }
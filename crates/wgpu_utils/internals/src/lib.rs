@@ -1,3 +1,8 @@
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` has no vertex format",
+    label = "doesn't implement ConstFormat",
+    note = "implement ConstFormat for it, or set one with #[vertex(format = \"...\")]"
+)]
 pub trait ConstFormat {
     const FORMAT: wgpu::VertexFormat;
 }
@@ -5,23 +10,105 @@ pub trait ConstFormat {
 pub const fn format_of<T: ConstFormat>() -> wgpu::VertexFormat {
     T::FORMAT
 }
+
+/// Integers read by the shader as floats normalized to [0, 1], such as
+/// ```Unorm<[u8; 4]>``` for an RGBA color packed in 4 bytes
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Unorm<T>(pub T);
+
+/// Integers read by the shader as floats normalized to [-1, 1], such as
+/// ```Snorm<[i16; 2]>``` for a packed normal
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snorm<T>(pub T);
+
+// Safety: transparent wrappers over the wrapped type
+unsafe impl<T: bytemuck::Zeroable> bytemuck::Zeroable for Unorm<T> {}
+unsafe impl<T: bytemuck::Pod> bytemuck::Pod for Unorm<T> {}
+unsafe impl<T: bytemuck::Zeroable> bytemuck::Zeroable for Snorm<T> {}
+unsafe impl<T: bytemuck::Pod> bytemuck::Pod for Snorm<T> {}
+
+/// Three 10 bit and one 2 bit unsigned integers packed in a u32, read by the
+/// shader as a vec4<f32> normalized to [0, 1]
+#[allow(non_camel_case_types)]
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Unorm10_10_10_2(pub u32);
+
+/// The bits of a half precision float, read by the shader as an f32
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct F16(pub u16);
+
+impl F16 {
+    /// Converts an f32, rounding to the nearest half. Values too large become
+    /// infinities and values too small subnormals or zero.
+    pub const fn from_f32(value: f32) -> Self {
+        let bits = value.to_bits();
+        let sign = ((bits >> 16) & 0x8000) as u16;
+        let exponent = ((bits >> 23) & 0xff) as i32;
+        let mantissa = bits & 0x7f_ffff;
+        if exponent == 0xff {
+            // Infinity, or NaN keeping it quiet
+            let nan = if mantissa != 0 { 0x200 } else { 0 };
+            return Self(sign | 0x7c00 | nan);
+        }
+        let exponent = exponent - 127 + 15;
+        if exponent >= 0x1f {
+            return Self(sign | 0x7c00);
+        }
+        if exponent <= 0 {
+            if exponent < -10 {
+                return Self(sign);
+            }
+            // Subnormal, shifting in the implicit leading bit
+            let mantissa = mantissa | 0x80_0000;
+            let shift = (14 - exponent) as u32;
+            let half = round(mantissa >> shift, mantissa, shift);
+            return Self(sign | half as u16);
+        }
+        // Rounding may carry into the exponent, up to infinity, which is correct
+        let half = round(((exponent as u32) << 10) | (mantissa >> 13), mantissa, 13);
+        Self(sign | half as u16)
+    }
+}
+
+impl From<f32> for F16 {
+    fn from(value: f32) -> Self {
+        Self::from_f32(value)
+    }
+}
+
+/// Rounds a value truncated from mantissa by dropping shift bits to the nearest,
+/// ties to even
+const fn round(truncated: u32, mantissa: u32, shift: u32) -> u32 {
+    let halfway = 1 << (shift - 1);
+    let dropped = mantissa & ((halfway << 1) - 1);
+    if dropped > halfway || (dropped == halfway && truncated & 1 == 1) {
+        truncated + 1
+    } else {
+        truncated
+    }
+}
 /// Defines the default VertexFormat of a particular type.
 /// By defining it using
-/// ``````
+/// ```ignore
 /// const_format_of!(MyVec3 => VertexFormat::Float32x3)
 /// ```
 ///
 /// You can then use its format with the following call
-/// ```
+/// ```ignore
 /// let vertex_format: wgpu::VertexFormat = format_of::<MyVec3>();
 /// ```
 ///
 /// And use this in conjunction with VertexAttributeArray macro
-/// ```
+/// ```ignore
 /// #[derive(VertexAttributeArray)]
 /// struct MyVertexBuffer{
 ///     pos: MyVec3,
 /// }
+/// ```
 macro_rules! const_format_of {
     ($T:ty => $format:expr) => {
         impl ConstFormat for $T {
@@ -30,6 +117,25 @@ macro_rules! const_format_of {
     };
 }
 
+const_format_of!([u8; 2] => wgpu::VertexFormat::Uint8x2);
+const_format_of!([u8; 4] => wgpu::VertexFormat::Uint8x4);
+const_format_of!([i8; 2] => wgpu::VertexFormat::Sint8x2);
+const_format_of!([i8; 4] => wgpu::VertexFormat::Sint8x4);
+const_format_of!([u16; 2] => wgpu::VertexFormat::Uint16x2);
+const_format_of!([u16; 4] => wgpu::VertexFormat::Uint16x4);
+const_format_of!([i16; 2] => wgpu::VertexFormat::Sint16x2);
+const_format_of!([i16; 4] => wgpu::VertexFormat::Sint16x4);
+const_format_of!(Unorm<[u8; 2]> => wgpu::VertexFormat::Unorm8x2);
+const_format_of!(Unorm<[u8; 4]> => wgpu::VertexFormat::Unorm8x4);
+const_format_of!(Snorm<[i8; 2]> => wgpu::VertexFormat::Snorm8x2);
+const_format_of!(Snorm<[i8; 4]> => wgpu::VertexFormat::Snorm8x4);
+const_format_of!(Unorm<[u16; 2]> => wgpu::VertexFormat::Unorm16x2);
+const_format_of!(Unorm<[u16; 4]> => wgpu::VertexFormat::Unorm16x4);
+const_format_of!(Snorm<[i16; 2]> => wgpu::VertexFormat::Snorm16x2);
+const_format_of!(Snorm<[i16; 4]> => wgpu::VertexFormat::Snorm16x4);
+const_format_of!(Unorm10_10_10_2 => wgpu::VertexFormat::Unorm10_10_10_2);
+const_format_of!([F16; 2] => wgpu::VertexFormat::Float16x2);
+const_format_of!([F16; 4] => wgpu::VertexFormat::Float16x4);
 const_format_of!(f32 => wgpu::VertexFormat::Float32);
 const_format_of!([f32; 2] => wgpu::VertexFormat::Float32x2);
 const_format_of!([f32; 3] => wgpu::VertexFormat::Float32x3);
@@ -37,3 +143,133 @@ const_format_of!([f32; 4] => wgpu::VertexFormat::Float32x4);
 const_format_of!(u32 => wgpu::VertexFormat::Uint32);
 const_format_of!([u32; 2] => wgpu::VertexFormat::Uint32x2);
 const_format_of!([u32; 3] => wgpu::VertexFormat::Uint32x3);
+const_format_of!([u32; 4] => wgpu::VertexFormat::Uint32x4);
+const_format_of!(i32 => wgpu::VertexFormat::Sint32);
+const_format_of!([i32; 2] => wgpu::VertexFormat::Sint32x2);
+const_format_of!([i32; 3] => wgpu::VertexFormat::Sint32x3);
+const_format_of!([i32; 4] => wgpu::VertexFormat::Sint32x4);
+const_format_of!(f64 => wgpu::VertexFormat::Float64);
+const_format_of!([f64; 2] => wgpu::VertexFormat::Float64x2);
+const_format_of!([f64; 3] => wgpu::VertexFormat::Float64x3);
+const_format_of!([f64; 4] => wgpu::VertexFormat::Float64x4);
+
+/// The WGSL type matching a Rust type in a uniform, with its alignment and size
+/// following the WGSL memory layout rules of the uniform address space
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` has no WGSL type",
+    label = "doesn't implement WgslType",
+    note = "implement WgslType for it with its WGSL name, alignment and size"
+)]
+pub trait WgslType {
+    const WGSL: &'static str;
+    const ALIGN: u64;
+    const SIZE: u64;
+}
+
+/// Implements WgslType, like const_format_of does for ConstFormat
+/// ```ignore
+/// wgsl_type_of!(MyVec3 => "vec3<f32>", align 16, size 12);
+/// ```
+macro_rules! wgsl_type_of {
+    ($T:ty => $wgsl:expr, align $align:literal, size $size:literal) => {
+        impl WgslType for $T {
+            const WGSL: &'static str = $wgsl;
+            const ALIGN: u64 = $align;
+            const SIZE: u64 = $size;
+        }
+    };
+}
+
+wgsl_type_of!(f32 => "f32", align 4, size 4);
+wgsl_type_of!([f32; 2] => "vec2<f32>", align 8, size 8);
+wgsl_type_of!([f32; 3] => "vec3<f32>", align 16, size 12);
+wgsl_type_of!([f32; 4] => "vec4<f32>", align 16, size 16);
+wgsl_type_of!(u32 => "u32", align 4, size 4);
+wgsl_type_of!([u32; 2] => "vec2<u32>", align 8, size 8);
+wgsl_type_of!([u32; 3] => "vec3<u32>", align 16, size 12);
+wgsl_type_of!([u32; 4] => "vec4<u32>", align 16, size 16);
+wgsl_type_of!(i32 => "i32", align 4, size 4);
+wgsl_type_of!([i32; 2] => "vec2<i32>", align 8, size 8);
+wgsl_type_of!([i32; 3] => "vec3<i32>", align 16, size 12);
+wgsl_type_of!([i32; 4] => "vec4<i32>", align 16, size 16);
+// Matrices are arrays of columns. Columns of 3 rows are padded to 16 bytes in
+// WGSL, so they have no plain array equivalent.
+wgsl_type_of!([[f32; 2]; 2] => "mat2x2<f32>", align 8, size 16);
+wgsl_type_of!([[f32; 2]; 3] => "mat3x2<f32>", align 8, size 24);
+wgsl_type_of!([[f32; 2]; 4] => "mat4x2<f32>", align 8, size 32);
+wgsl_type_of!([[f32; 4]; 2] => "mat2x4<f32>", align 16, size 32);
+wgsl_type_of!([[f32; 4]; 3] => "mat3x4<f32>", align 16, size 48);
+wgsl_type_of!([[f32; 4]; 4] => "mat4x4<f32>", align 16, size 64);
+
+#[cfg(feature = "glam")]
+mod glam_formats {
+    use super::ConstFormat;
+
+    const_format_of!(glam::Vec2 => wgpu::VertexFormat::Float32x2);
+    const_format_of!(glam::Vec3 => wgpu::VertexFormat::Float32x3);
+    const_format_of!(glam::Vec4 => wgpu::VertexFormat::Float32x4);
+    const_format_of!(glam::UVec2 => wgpu::VertexFormat::Uint32x2);
+    const_format_of!(glam::UVec3 => wgpu::VertexFormat::Uint32x3);
+    const_format_of!(glam::UVec4 => wgpu::VertexFormat::Uint32x4);
+    const_format_of!(glam::IVec2 => wgpu::VertexFormat::Sint32x2);
+    const_format_of!(glam::IVec3 => wgpu::VertexFormat::Sint32x3);
+    const_format_of!(glam::IVec4 => wgpu::VertexFormat::Sint32x4);
+    const_format_of!(glam::DVec2 => wgpu::VertexFormat::Float64x2);
+    const_format_of!(glam::DVec3 => wgpu::VertexFormat::Float64x3);
+    const_format_of!(glam::DVec4 => wgpu::VertexFormat::Float64x4);
+
+    use super::WgslType;
+
+    wgsl_type_of!(glam::Vec2 => "vec2<f32>", align 8, size 8);
+    wgsl_type_of!(glam::Vec3 => "vec3<f32>", align 16, size 12);
+    wgsl_type_of!(glam::Vec3A => "vec3<f32>", align 16, size 12);
+    wgsl_type_of!(glam::Vec4 => "vec4<f32>", align 16, size 16);
+    wgsl_type_of!(glam::UVec2 => "vec2<u32>", align 8, size 8);
+    wgsl_type_of!(glam::UVec3 => "vec3<u32>", align 16, size 12);
+    wgsl_type_of!(glam::UVec4 => "vec4<u32>", align 16, size 16);
+    wgsl_type_of!(glam::IVec2 => "vec2<i32>", align 8, size 8);
+    wgsl_type_of!(glam::IVec3 => "vec3<i32>", align 16, size 12);
+    wgsl_type_of!(glam::IVec4 => "vec4<i32>", align 16, size 16);
+    wgsl_type_of!(glam::Mat2 => "mat2x2<f32>", align 8, size 16);
+    wgsl_type_of!(glam::Mat3A => "mat3x3<f32>", align 16, size 48);
+    wgsl_type_of!(glam::Mat4 => "mat4x4<f32>", align 16, size 64);
+}
+
+#[cfg(feature = "mint")]
+mod mint_formats {
+    use super::ConstFormat;
+
+    /// Implements ConstFormat for the mint vectors and points of a scalar
+    macro_rules! mint_formats {
+        ($scalar:ty => $x2:ident, $x3:ident, $x4:ident) => {
+            const_format_of!(mint::Vector2<$scalar> => wgpu::VertexFormat::$x2);
+            const_format_of!(mint::Vector3<$scalar> => wgpu::VertexFormat::$x3);
+            const_format_of!(mint::Vector4<$scalar> => wgpu::VertexFormat::$x4);
+            const_format_of!(mint::Point2<$scalar> => wgpu::VertexFormat::$x2);
+            const_format_of!(mint::Point3<$scalar> => wgpu::VertexFormat::$x3);
+        };
+    }
+
+    mint_formats!(f32 => Float32x2, Float32x3, Float32x4);
+    mint_formats!(u32 => Uint32x2, Uint32x3, Uint32x4);
+    mint_formats!(i32 => Sint32x2, Sint32x3, Sint32x4);
+    mint_formats!(f64 => Float64x2, Float64x3, Float64x4);
+
+    use super::WgslType;
+
+    /// Implements WgslType for the mint vectors and points of a scalar
+    macro_rules! mint_wgsl_types {
+        ($scalar:literal, $T:ty) => {
+            wgsl_type_of!(mint::Vector2<$T> => concat!("vec2<", $scalar, ">"), align 8, size 8);
+            wgsl_type_of!(mint::Vector3<$T> => concat!("vec3<", $scalar, ">"), align 16, size 12);
+            wgsl_type_of!(mint::Vector4<$T> => concat!("vec4<", $scalar, ">"), align 16, size 16);
+            wgsl_type_of!(mint::Point2<$T> => concat!("vec2<", $scalar, ">"), align 8, size 8);
+            wgsl_type_of!(mint::Point3<$T> => concat!("vec3<", $scalar, ">"), align 16, size 12);
+        };
+    }
+
+    mint_wgsl_types!("f32", f32);
+    mint_wgsl_types!("u32", u32);
+    mint_wgsl_types!("i32", i32);
+    wgsl_type_of!(mint::ColumnMatrix4<f32> => "mat4x4<f32>", align 16, size 64);
+}
@@ -1,8 +1,68 @@
-pub use internals::format_of;
-pub use vertex_attribute_derive::VertexAttributeArray;
+pub mod reflect;
+pub mod wgsl;
+
+pub use internals::{format_of, ConstFormat, Snorm, Unorm, Unorm10_10_10_2, WgslType, F16};
+pub use vertex_attribute_derive::{Bindable, UniformLayout, VertexAttributeArray, WgslStruct};
 
 pub trait VertexAttributeArray {
+    /// The VertexBufferLayout of the type, evaluated at compile time
+    const LAYOUT: wgpu::VertexBufferLayout<'static>;
+
     /// Generates a VertexBufferLayout to be used in a RenderPipeline
-    /// TODO: refactor static out of it.
-    fn desc() -> wgpu::VertexBufferLayout<'static>;
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        Self::LAYOUT
+    }
+}
+
+/// A type with a matching WGSL struct declaration, so shaders can include it
+/// through a WgslPreprocessor rather than keeping a copy in sync by hand
+pub trait WgslStruct {
+    /// The name of the struct in WGSL
+    const NAME: &'static str;
+
+    /// Generates the WGSL declaration of the struct
+    fn wgsl() -> String;
+}
+
+/// A type that can be written to a uniform buffer, with its layout checked against
+/// the WGSL rules for uniforms at compile time
+pub trait UniformLayout: Sized {
+    /// The members of the struct, with their WGSL types at their Rust offsets
+    const MEMBERS: &'static [wgsl::Member];
+
+    /// Fails to compile when the layout doesn't follow the WGSL rules for uniforms,
+    /// evaluated by using it, as in ```let () = T::CHECK;```
+    const CHECK: () = wgsl::check_uniform_layout(Self::MEMBERS, std::mem::size_of::<Self>());
+}
+
+/// A group of resources bound together to a pipeline, such as uniform and storage
+/// buffers, textures and samplers
+pub trait Bindable {
+    /// The entries of the BindGroupLayout, evaluated at compile time
+    const ENTRIES: &'static [wgpu::BindGroupLayoutEntry];
+
+    /// Generates a BindGroupLayoutDescriptor to create the layout of the group
+    fn layout_desc<'a>() -> wgpu::BindGroupLayoutDescriptor<'a> {
+        wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: Self::ENTRIES,
+        }
+    }
+
+    /// The resources bound to each entry of the layout
+    fn entries(&self) -> Vec<wgpu::BindGroupEntry<'_>>;
+
+    /// Creates a bind group of the resources, for a layout created from layout_desc
+    fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        label: Option<&str>,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label,
+            layout,
+            entries: &self.entries(),
+        })
+    }
 }
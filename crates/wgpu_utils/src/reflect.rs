@@ -0,0 +1,341 @@
+use std::fmt;
+
+use crate::wgsl::vertex_type;
+
+/// Errors found while checking the layouts of a pipeline against its shader
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReflectionError {
+    /// The shader failed to parse or validate, with naga's formatted error
+    InvalidShader(String),
+    MissingEntryPoint(String),
+    NotVertexEntryPoint(String),
+    /// A vertex input no vertex buffer provides
+    MissingAttribute {
+        location: u32,
+        shader_type: String,
+    },
+    /// A vertex input whose type doesn't match the format of its attribute
+    AttributeMismatch {
+        location: u32,
+        format: wgpu::VertexFormat,
+        shader_type: String,
+    },
+    /// A binding used by the shader that isn't in the bind group layouts
+    MissingBinding {
+        group: u32,
+        binding: u32,
+    },
+    /// A binding of a different type in the shader than in its layout
+    BindingMismatch {
+        group: u32,
+        binding: u32,
+        shader_type: String,
+    },
+    /// A binding used by a stage its layout doesn't make it visible to
+    NotVisible {
+        group: u32,
+        binding: u32,
+        stage: wgpu::ShaderStages,
+    },
+}
+
+impl fmt::Display for ReflectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReflectionError::InvalidShader(error) => write!(f, "invalid shader:\n{error}"),
+            ReflectionError::MissingEntryPoint(name) => {
+                write!(f, "the shader has no entry point named {name}")
+            }
+            ReflectionError::NotVertexEntryPoint(name) => {
+                write!(f, "{name} isn't a vertex entry point")
+            }
+            ReflectionError::MissingAttribute {
+                location,
+                shader_type,
+            } => write!(
+                f,
+                "the {shader_type} vertex input at location {location} isn't in any vertex buffer"
+            ),
+            ReflectionError::AttributeMismatch {
+                location,
+                format,
+                shader_type,
+            } => write!(
+                f,
+                "the vertex input at location {location} is a {shader_type} in the shader, \
+                 but its {format:?} attribute is read as a {}",
+                vertex_type(*format)
+            ),
+            ReflectionError::MissingBinding { group, binding } => write!(
+                f,
+                "binding {binding} of group {group} isn't in the bind group layouts"
+            ),
+            ReflectionError::BindingMismatch {
+                group,
+                binding,
+                shader_type,
+            } => write!(
+                f,
+                "binding {binding} of group {group} is a {shader_type} in the shader, \
+                 which doesn't match its layout"
+            ),
+            ReflectionError::NotVisible {
+                group,
+                binding,
+                stage,
+            } => write!(
+                f,
+                "binding {binding} of group {group} isn't visible to the {stage:?} stage using it"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReflectionError {}
+
+/// A WGSL shader parsed and validated with naga, to check the layouts a pipeline
+/// is created with against what the shader declares. Unlike the wgpu validation
+/// errors it reports the exact location or binding that disagrees, and it runs
+/// without a GPU, so it can be used in tests.
+pub struct ShaderReflection {
+    module: naga::Module,
+    info: naga::valid::ModuleInfo,
+}
+
+impl ShaderReflection {
+    pub fn parse(source: &str) -> Result<Self, ReflectionError> {
+        let module = naga::front::wgsl::parse_str(source)
+            .map_err(|error| ReflectionError::InvalidShader(error.emit_to_string(source)))?;
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .map_err(|error| ReflectionError::InvalidShader(error.emit_to_string(source)))?;
+        Ok(Self { module, info })
+    }
+
+    /// Checks that every input of a vertex entry point is provided by one of the
+    /// vertex buffers, as the type its format is read as. Stricter than wgpu,
+    /// which also accepts inputs with another number of components.
+    pub fn check_vertex_layouts(
+        &self,
+        entry_point: &str,
+        layouts: &[wgpu::VertexBufferLayout],
+    ) -> Result<(), ReflectionError> {
+        let (_, entry) = self.entry_point(entry_point)?;
+        if entry.stage != naga::ShaderStage::Vertex {
+            return Err(ReflectionError::NotVertexEntryPoint(
+                entry_point.to_string(),
+            ));
+        }
+        let attributes = || layouts.iter().flat_map(|layout| layout.attributes);
+        for (location, ty) in self.vertex_inputs(&entry.function) {
+            let shader_type = ty.to_wgsl(&self.module.to_ctx());
+            let attribute = attributes()
+                .find(|attribute| attribute.shader_location == location)
+                .ok_or_else(|| ReflectionError::MissingAttribute {
+                    location,
+                    shader_type: shader_type.clone(),
+                })?;
+            if vertex_type(attribute.format) != shader_type {
+                return Err(ReflectionError::AttributeMismatch {
+                    location,
+                    format: attribute.format,
+                    shader_type,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that every binding used by the entry points is in the bind group
+    /// layouts, indexed by group, with a matching type and visibility
+    pub fn check_bind_groups(
+        &self,
+        entry_points: &[&str],
+        layouts: &[wgpu::BindGroupLayoutDescriptor],
+    ) -> Result<(), ReflectionError> {
+        for name in entry_points {
+            let (index, entry) = self.entry_point(name)?;
+            let stage = shader_stage(entry.stage);
+            let uses = self.info.get_entry_point(index);
+            for (handle, variable) in self.module.global_variables.iter() {
+                let Some(binding) = &variable.binding else {
+                    continue;
+                };
+                if uses[handle].is_empty() {
+                    continue;
+                }
+                let (group, binding) = (binding.group, binding.binding);
+                let layout_entry = layouts
+                    .get(group as usize)
+                    .and_then(|layout| layout.entries.iter().find(|entry| entry.binding == binding))
+                    .ok_or(ReflectionError::MissingBinding { group, binding })?;
+                if !self.binding_matches(variable, &layout_entry.ty) {
+                    return Err(ReflectionError::BindingMismatch {
+                        group,
+                        binding,
+                        shader_type: self.binding_type(variable),
+                    });
+                }
+                if !layout_entry.visibility.contains(stage) {
+                    return Err(ReflectionError::NotVisible {
+                        group,
+                        binding,
+                        stage,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The stage of the entry point with the given name, if the shader has one
+    pub fn entry_point_stage(&self, name: &str) -> Option<wgpu::ShaderStages> {
+        let (_, entry) = self.entry_point(name).ok()?;
+        Some(shader_stage(entry.stage))
+    }
+
+    fn entry_point(&self, name: &str) -> Result<(usize, &naga::EntryPoint), ReflectionError> {
+        self.module
+            .entry_points
+            .iter()
+            .enumerate()
+            .find(|(_, entry)| entry.name == name)
+            .ok_or_else(|| ReflectionError::MissingEntryPoint(name.to_string()))
+    }
+
+    /// The location and type of the inputs of a function, either arguments or the
+    /// members of struct arguments, leaving out builtins
+    fn vertex_inputs(&self, function: &naga::Function) -> Vec<(u32, naga::Handle<naga::Type>)> {
+        let mut inputs = Vec::new();
+        for argument in &function.arguments {
+            match (&argument.binding, &self.module.types[argument.ty].inner) {
+                (Some(naga::Binding::Location { location, .. }), _) => {
+                    inputs.push((*location, argument.ty))
+                }
+                (None, naga::TypeInner::Struct { members, .. }) => {
+                    inputs.extend(members.iter().filter_map(|member| match member.binding {
+                        Some(naga::Binding::Location { location, .. }) => {
+                            Some((location, member.ty))
+                        }
+                        _ => None,
+                    }))
+                }
+                _ => {}
+            }
+        }
+        inputs
+    }
+
+    fn binding_matches(&self, variable: &naga::GlobalVariable, ty: &wgpu::BindingType) -> bool {
+        use wgpu::BindingType;
+        match (variable.space, ty) {
+            (naga::AddressSpace::Uniform, BindingType::Buffer { ty, .. }) => {
+                *ty == wgpu::BufferBindingType::Uniform
+            }
+            (naga::AddressSpace::Storage { access }, BindingType::Buffer { ty, .. }) => match ty {
+                wgpu::BufferBindingType::Storage { read_only } => {
+                    !read_only || !access.contains(naga::StorageAccess::STORE)
+                }
+                wgpu::BufferBindingType::Uniform => false,
+            },
+            (naga::AddressSpace::Handle, ty) => {
+                let mut inner = &self.module.types[variable.ty].inner;
+                if let naga::TypeInner::BindingArray { base, .. } = inner {
+                    inner = &self.module.types[*base].inner;
+                }
+                handle_matches(inner, ty)
+            }
+            _ => false,
+        }
+    }
+
+    /// Describes a binding as declared in the shader, such as uniform CameraUniform
+    fn binding_type(&self, variable: &naga::GlobalVariable) -> String {
+        let ty = variable.ty.to_wgsl(&self.module.to_ctx());
+        match variable.space {
+            naga::AddressSpace::Uniform => format!("uniform {ty}"),
+            naga::AddressSpace::Storage { access } => {
+                if access.contains(naga::StorageAccess::STORE) {
+                    format!("read_write storage {ty}")
+                } else {
+                    format!("read only storage {ty}")
+                }
+            }
+            _ => ty,
+        }
+    }
+}
+
+fn shader_stage(stage: naga::ShaderStage) -> wgpu::ShaderStages {
+    match stage {
+        naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+        naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+        naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+    }
+}
+
+/// Whether a texture or sampler in the shader matches the type of its layout
+fn handle_matches(inner: &naga::TypeInner, ty: &wgpu::BindingType) -> bool {
+    use wgpu::{BindingType, TextureSampleType};
+    match (inner, ty) {
+        (naga::TypeInner::Sampler { comparison }, BindingType::Sampler(sampler)) => {
+            *comparison == (*sampler == wgpu::SamplerBindingType::Comparison)
+        }
+        (
+            naga::TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            },
+            BindingType::Texture {
+                sample_type,
+                view_dimension,
+                multisampled,
+            },
+        ) => {
+            let class_matches = match class {
+                naga::ImageClass::Sampled { kind, multi } => {
+                    multi == multisampled
+                        && match kind {
+                            // Depth textures can be read as floats too
+                            naga::ScalarKind::Float => matches!(
+                                sample_type,
+                                TextureSampleType::Float { .. } | TextureSampleType::Depth
+                            ),
+                            naga::ScalarKind::Sint => *sample_type == TextureSampleType::Sint,
+                            naga::ScalarKind::Uint => *sample_type == TextureSampleType::Uint,
+                            _ => false,
+                        }
+                }
+                naga::ImageClass::Depth { multi } => {
+                    multi == multisampled && *sample_type == TextureSampleType::Depth
+                }
+                naga::ImageClass::Storage { .. } => false,
+            };
+            class_matches && view_dimension_of(*dim, *arrayed) == *view_dimension
+        }
+        (
+            naga::TypeInner::Image {
+                dim,
+                arrayed,
+                class: naga::ImageClass::Storage { .. },
+            },
+            BindingType::StorageTexture { view_dimension, .. },
+        ) => view_dimension_of(*dim, *arrayed) == *view_dimension,
+        _ => false,
+    }
+}
+
+fn view_dimension_of(dim: naga::ImageDimension, arrayed: bool) -> wgpu::TextureViewDimension {
+    match (dim, arrayed) {
+        (naga::ImageDimension::D1, _) => wgpu::TextureViewDimension::D1,
+        (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+        (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+        (naga::ImageDimension::D3, _) => wgpu::TextureViewDimension::D3,
+        (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+        (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+
+use crate::{WgslStruct, WgslType};
+
+/// A field of a uniform struct, with its WGSL type and its offset in Rust
+#[derive(Clone, Copy, Debug)]
+pub struct Member {
+    pub name: &'static str,
+    pub wgsl: &'static str,
+    pub align: u64,
+    pub size: u64,
+    pub offset: u64,
+}
+
+impl Member {
+    pub const fn of<T: WgslType>(name: &'static str, offset: usize) -> Self {
+        Self {
+            name,
+            wgsl: T::WGSL,
+            align: T::ALIGN,
+            size: T::SIZE,
+            offset: offset as u64,
+        }
+    }
+}
+
+/// Declares a uniform struct with its members at their Rust offsets, adding a
+/// @size to the members followed by more padding than WGSL would add.
+///
+/// Panics if a member is placed before where WGSL aligns it, or if the struct is
+/// smaller than its WGSL equivalent, as the shader would read past it.
+pub fn uniform_struct(name: &str, members: &[Member], size: usize) -> String {
+    let mut sizes: Vec<u64> = members.iter().map(|member| member.size).collect();
+    let mut end: u64 = 0;
+    let mut align = 1;
+    for (i, member) in members.iter().enumerate() {
+        let wgsl_offset = end.next_multiple_of(member.align);
+        if member.offset != wgsl_offset {
+            assert!(
+                i > 0 && member.offset > wgsl_offset && member.offset % member.align == 0,
+                "{name}.{} is at offset {} but WGSL places a {} at offset {wgsl_offset} in uniforms",
+                member.name,
+                member.offset,
+                member.wgsl,
+            );
+            sizes[i - 1] = member.offset - members[i - 1].offset;
+        }
+        end = member.offset + member.size;
+        align = align.max(member.align);
+    }
+    let size = size as u64;
+    let wgsl_size = end.next_multiple_of(align);
+    assert!(
+        size >= wgsl_size,
+        "{name} is {size} bytes but WGSL needs {wgsl_size} bytes for it in uniforms"
+    );
+    if let Some(last) = members.last() {
+        if size > wgsl_size {
+            sizes[members.len() - 1] = size - last.offset;
+        }
+    }
+
+    let mut source = format!("struct {name} {{\n");
+    for (member, size) in members.iter().zip(sizes) {
+        let attribute = if size == member.size {
+            String::new()
+        } else {
+            format!("@size({size}) ")
+        };
+        writeln!(source, "    {attribute}{}: {},", member.name, member.wgsl).unwrap();
+    }
+    source.push_str("};\n");
+    source
+}
+
+/// Checks the members against the WGSL rules for uniforms like uniform_struct does,
+/// but at compile time, when evaluated by UniformLayout::CHECK. The struct also needs
+/// to be padded to a multiple of 16 bytes, as WGSL aligns structs in uniforms to 16
+/// bytes, so the same type can be nested in other uniforms or arrays of them.
+pub const fn check_uniform_layout(members: &[Member], size: usize) {
+    let mut end: u64 = 0;
+    let mut align = 1;
+    let mut i = 0;
+    while i < members.len() {
+        let member = &members[i];
+        let wgsl_offset = end.next_multiple_of(member.align);
+        // Members after the WGSL offset are fine, the previous one gets a @size
+        assert!(
+            member.offset == wgsl_offset
+                || (i > 0
+                    && member.offset > wgsl_offset
+                    && member.offset.is_multiple_of(member.align)),
+            "a uniform member is placed before where WGSL aligns it, such as a \
+             vec3 or vec4 after a scalar without padding"
+        );
+        end = member.offset + member.size;
+        if member.align > align {
+            align = member.align;
+        }
+        i += 1;
+    }
+    let size = size as u64;
+    assert!(
+        size >= end.next_multiple_of(align),
+        "a uniform is smaller than WGSL needs, pad it up to the alignment of its members"
+    );
+    assert!(
+        size.is_multiple_of(16),
+        "uniforms need to be padded to a multiple of 16 bytes"
+    );
+}
+
+/// Declares a vertex shader input with a member per attribute, named in order
+pub fn vertex_struct(name: &str, names: &[&str], attributes: &[wgpu::VertexAttribute]) -> String {
+    debug_assert_eq!(names.len(), attributes.len());
+    let mut source = format!("struct {name} {{\n");
+    for (member, attribute) in names.iter().zip(attributes) {
+        writeln!(
+            source,
+            "    @location({}) {member}: {},",
+            attribute.shader_location,
+            vertex_type(attribute.format)
+        )
+        .unwrap();
+    }
+    source.push_str("};\n");
+    source
+}
+
+/// The type a vertex format is read as in WGSL
+pub fn vertex_type(format: wgpu::VertexFormat) -> &'static str {
+    use wgpu::VertexFormat::*;
+    match format {
+        Uint8x2 | Uint16x2 | Uint32x2 => "vec2<u32>",
+        Uint32x3 => "vec3<u32>",
+        Uint8x4 | Uint16x4 | Uint32x4 => "vec4<u32>",
+        Sint8x2 | Sint16x2 | Sint32x2 => "vec2<i32>",
+        Sint32x3 => "vec3<i32>",
+        Sint8x4 | Sint16x4 | Sint32x4 => "vec4<i32>",
+        Unorm8x2 | Snorm8x2 | Unorm16x2 | Snorm16x2 | Float16x2 | Float32x2 => "vec2<f32>",
+        Float32x3 => "vec3<f32>",
+        Unorm8x4 | Snorm8x4 | Unorm16x4 | Snorm16x4 | Float16x4 | Float32x4 | Unorm10_10_10_2 => {
+            "vec4<f32>"
+        }
+        Uint32 => "u32",
+        Sint32 => "i32",
+        Float32 => "f32",
+        Float64 => "f64",
+        Float64x2 => "vec2<f64>",
+        Float64x3 => "vec3<f64>",
+        Float64x4 => "vec4<f64>",
+    }
+}
+
+/// Errors found while preprocessing WGSL, with the line they were found at and
+/// the snippet that line is in, None for the processed source itself
+#[derive(Debug, PartialEq)]
+pub enum PreprocessError {
+    /// An #include without a quoted name
+    InvalidInclude {
+        line: usize,
+        snippet: Option<String>,
+    },
+    /// An #include of a snippet the preprocessor doesn't have
+    UnknownInclude {
+        name: String,
+        line: usize,
+        snippet: Option<String>,
+    },
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = |f: &mut fmt::Formatter<'_>, line, snippet: &Option<String>| {
+            write!(f, "line {line}")?;
+            match snippet {
+                Some(snippet) => write!(f, " of \"{snippet}\""),
+                None => Ok(()),
+            }
+        };
+        match self {
+            Self::InvalidInclude { line, snippet } => {
+                write!(f, "invalid #include at ")?;
+                location(f, line, snippet)?;
+                write!(f, ", expected #include \"Name\"")
+            }
+            Self::UnknownInclude {
+                name,
+                line,
+                snippet,
+            } => {
+                write!(f, "unknown include \"{name}\" at ")?;
+                location(f, line, snippet)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// Replaces the ```#include "Name"``` lines of WGSL sources with the snippets
+/// added under that name, such as the declarations of WgslStruct types. Snippets
+/// may include others, and each is included once, so shared declarations can be
+/// included wherever they're needed.
+#[derive(Clone, Debug, Default)]
+pub struct WgslPreprocessor {
+    snippets: HashMap<String, String>,
+}
+
+impl WgslPreprocessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the declaration of a WgslStruct, included by its name
+    pub fn with_struct<T: WgslStruct>(self) -> Self {
+        self.with_snippet(T::NAME, T::wgsl())
+    }
+
+    /// Adds a snippet of WGSL, included by name
+    pub fn with_snippet(mut self, name: impl Into<String>, source: impl Into<String>) -> Self {
+        self.snippets.insert(name.into(), source.into());
+        self
+    }
+
+    /// Expands the includes of a source
+    pub fn process(&self, source: &str) -> Result<String, PreprocessError> {
+        let mut output = String::with_capacity(source.len());
+        self.expand(source, None, &mut HashSet::new(), &mut output)?;
+        Ok(output)
+    }
+
+    /// Appends source to output with its includes expanded, snippet is the name
+    /// source was included by
+    fn expand<'a>(
+        &'a self,
+        source: &str,
+        snippet: Option<&str>,
+        included: &mut HashSet<&'a str>,
+        output: &mut String,
+    ) -> Result<(), PreprocessError> {
+        for (i, line) in source.lines().enumerate() {
+            let Some(directive) = line.trim().strip_prefix("#include") else {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            };
+            let name = directive
+                .trim()
+                .strip_prefix('"')
+                .and_then(|name| name.strip_suffix('"'))
+                .filter(|name| !name.is_empty() && !name.contains('"'))
+                .ok_or_else(|| PreprocessError::InvalidInclude {
+                    line: i + 1,
+                    snippet: snippet.map(str::to_string),
+                })?;
+            let (name, snippet) = self.snippets.get_key_value(name).ok_or_else(|| {
+                PreprocessError::UnknownInclude {
+                    name: name.to_string(),
+                    line: i + 1,
+                    snippet: snippet.map(str::to_string),
+                }
+            })?;
+            if included.insert(name) {
+                self.expand(snippet, Some(name), included, output)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+// Fields are only read through the generated bind groups
+#![allow(dead_code)]
+
+use wgpu::ShaderStages as Stages;
+use wgpu_utils::reflect::ShaderReflection;
+use wgpu_utils::Bindable;
+
+/// Camera, time and palette data bound together in a single group
+#[derive(Bindable)]
+struct FrameBindings<'a> {
+    #[uniform]
+    camera: &'a wgpu::Buffer,
+    #[uniform(visibility = "fragment")]
+    time: &'a wgpu::Buffer,
+    #[storage(read_only, binding = 4, visibility = "fragment")]
+    palette: &'a wgpu::Buffer,
+    #[texture(visibility = "fragment")]
+    noise: &'a wgpu::TextureView,
+    #[sampler(visibility = "fragment")]
+    noise_sampler: &'a wgpu::Sampler,
+}
+
+#[derive(Bindable)]
+struct ShadowBindings {
+    #[texture(sample_type = "depth", dimension = "D2Array")]
+    shadow_maps: wgpu::TextureView,
+    #[sampler(comparison, visibility = "fragment")]
+    shadow_sampler: wgpu::Sampler,
+    #[storage(binding = 3, visibility = "vertex | fragment | compute")]
+    lights: wgpu::Buffer,
+    #[texture(sample_type = "uint", multisampled)]
+    ids: wgpu::TextureView,
+    #[sampler(non_filtering)]
+    ids_sampler: wgpu::Sampler,
+}
+
+fn entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    ty: wgpu::BindingType,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty,
+        count: None,
+    }
+}
+
+fn buffer(ty: wgpu::BufferBindingType) -> wgpu::BindingType {
+    wgpu::BindingType::Buffer {
+        ty,
+        has_dynamic_offset: false,
+        min_binding_size: None,
+    }
+}
+
+#[test]
+fn multi_entry_layout() {
+    let desc = FrameBindings::layout_desc();
+    assert_eq!(desc.label, Some("frame_bindings_layout"));
+    assert_eq!(
+        desc.entries,
+        [
+            entry(
+                0,
+                Stages::VERTEX_FRAGMENT,
+                buffer(wgpu::BufferBindingType::Uniform)
+            ),
+            entry(
+                1,
+                Stages::FRAGMENT,
+                buffer(wgpu::BufferBindingType::Uniform)
+            ),
+            entry(
+                4,
+                Stages::FRAGMENT,
+                buffer(wgpu::BufferBindingType::Storage { read_only: true })
+            ),
+            entry(
+                5,
+                Stages::FRAGMENT,
+                wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                }
+            ),
+            entry(
+                6,
+                Stages::FRAGMENT,
+                wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn resource_options() {
+    assert_eq!(
+        ShadowBindings::ENTRIES,
+        [
+            entry(
+                0,
+                Stages::VERTEX_FRAGMENT,
+                wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Depth,
+                    view_dimension: wgpu::TextureViewDimension::D2Array,
+                    multisampled: false,
+                }
+            ),
+            entry(
+                1,
+                Stages::FRAGMENT,
+                wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison)
+            ),
+            entry(
+                3,
+                Stages::all(),
+                buffer(wgpu::BufferBindingType::Storage { read_only: false })
+            ),
+            entry(
+                4,
+                Stages::VERTEX_FRAGMENT,
+                wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Uint,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: true,
+                }
+            ),
+            entry(
+                5,
+                Stages::VERTEX_FRAGMENT,
+                wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn layout_matches_shader() {
+    const SHADER: &str = r#"
+@group(0) @binding(0)
+var<uniform> camera: mat4x4<f32>;
+@group(0) @binding(1)
+var<uniform> time: f32;
+@group(0) @binding(4)
+var<storage, read> palette: array<vec4<f32>>;
+@group(0) @binding(5)
+var noise: texture_2d<f32>;
+@group(0) @binding(6)
+var noise_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = camera * vec4<f32>(position, 0.0, 1.0);
+    out.uv = position;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let shade = textureSample(noise, noise_sampler, in.uv + vec2<f32>(time)).r;
+    return palette[u32(shade * f32(arrayLength(&palette)))];
+}
+"#;
+    let shader = ShaderReflection::parse(SHADER).unwrap();
+    assert_eq!(
+        shader.check_bind_groups(&["vs_main", "fs_main"], &[FrameBindings::layout_desc()]),
+        Ok(())
+    );
+}
@@ -0,0 +1,317 @@
+// Fields are only read through the generated layouts
+#![allow(dead_code)]
+
+use wgpu_utils::reflect::{ReflectionError, ShaderReflection};
+use wgpu_utils::wgsl::WgslPreprocessor;
+use wgpu_utils::{VertexAttributeArray, WgslStruct};
+
+const SHADER: &str = r#"
+struct Camera {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var color_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var color_sampler: sampler;
+// Declared but used by no entry point
+@group(2) @binding(0)
+var<storage, read_write> unused: array<u32>;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32,
+    vertex: VertexInput,
+    @location(4) id: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(vertex.position, f32(id + index), 1.0);
+    out.uv = vertex.uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(color_texture, color_sampler, in.uv);
+}
+"#;
+
+const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+    wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2];
+const INSTANCE_ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![4 => Uint32];
+
+fn vertex_layouts<'a>(
+    vertex: &'a [wgpu::VertexAttribute],
+    instance: &'a [wgpu::VertexAttribute],
+) -> [wgpu::VertexBufferLayout<'a>; 2] {
+    [
+        wgpu::VertexBufferLayout {
+            array_stride: 16,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: vertex,
+        },
+        wgpu::VertexBufferLayout {
+            array_stride: 4,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: instance,
+        },
+    ]
+}
+
+fn entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    ty: wgpu::BindingType,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty,
+        count: None,
+    }
+}
+
+const UNIFORM: wgpu::BindingType = wgpu::BindingType::Buffer {
+    ty: wgpu::BufferBindingType::Uniform,
+    has_dynamic_offset: false,
+    min_binding_size: None,
+};
+const TEXTURE: wgpu::BindingType = wgpu::BindingType::Texture {
+    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+    view_dimension: wgpu::TextureViewDimension::D2,
+    multisampled: false,
+};
+const SAMPLER: wgpu::BindingType = wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering);
+
+fn check_bind_groups(
+    camera: &[wgpu::BindGroupLayoutEntry],
+    material: &[wgpu::BindGroupLayoutEntry],
+) -> Result<(), ReflectionError> {
+    let layouts = [camera, material].map(|entries| wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries,
+    });
+    ShaderReflection::parse(SHADER)?.check_bind_groups(&["vs_main", "fs_main"], &layouts)
+}
+
+#[test]
+fn matching_vertex_layouts() {
+    let shader = ShaderReflection::parse(SHADER).unwrap();
+    let layouts = vertex_layouts(&VERTEX_ATTRIBUTES, &INSTANCE_ATTRIBUTES);
+    assert_eq!(shader.check_vertex_layouts("vs_main", &layouts), Ok(()));
+}
+
+#[test]
+fn vertex_layout_mismatches() {
+    let shader = ShaderReflection::parse(SHADER).unwrap();
+
+    let layouts = vertex_layouts(&VERTEX_ATTRIBUTES, &[]);
+    assert_eq!(
+        shader.check_vertex_layouts("vs_main", &layouts),
+        Err(ReflectionError::MissingAttribute {
+            location: 4,
+            shader_type: "u32".to_string(),
+        })
+    );
+
+    let uv_as_vec3 = wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x3];
+    let layouts = vertex_layouts(&uv_as_vec3, &INSTANCE_ATTRIBUTES);
+    let error = shader
+        .check_vertex_layouts("vs_main", &layouts)
+        .unwrap_err();
+    assert_eq!(
+        error,
+        ReflectionError::AttributeMismatch {
+            location: 1,
+            format: wgpu::VertexFormat::Float32x3,
+            shader_type: "vec2<f32>".to_string(),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "the vertex input at location 1 is a vec2<f32> in the shader, \
+         but its Float32x3 attribute is read as a vec3<f32>"
+    );
+
+    let signed_id = wgpu::vertex_attr_array![4 => Sint32];
+    let layouts = vertex_layouts(&VERTEX_ATTRIBUTES, &signed_id);
+    assert!(matches!(
+        shader.check_vertex_layouts("vs_main", &layouts),
+        Err(ReflectionError::AttributeMismatch { location: 4, .. })
+    ));
+}
+
+#[test]
+fn entry_points() {
+    let shader = ShaderReflection::parse(SHADER).unwrap();
+    assert_eq!(
+        shader.check_vertex_layouts("vs_other", &[]),
+        Err(ReflectionError::MissingEntryPoint("vs_other".to_string()))
+    );
+    assert_eq!(
+        shader.check_vertex_layouts("fs_main", &[]),
+        Err(ReflectionError::NotVertexEntryPoint("fs_main".to_string()))
+    );
+    assert_eq!(
+        shader.entry_point_stage("vs_main"),
+        Some(wgpu::ShaderStages::VERTEX)
+    );
+    assert_eq!(
+        shader.entry_point_stage("fs_main"),
+        Some(wgpu::ShaderStages::FRAGMENT)
+    );
+    assert_eq!(shader.entry_point_stage("vs_other"), None);
+}
+
+#[test]
+fn invalid_shader() {
+    let source = SHADER.replace("vertex.uv;", "vertex.normal;");
+    assert!(matches!(
+        ShaderReflection::parse(&source),
+        Err(ReflectionError::InvalidShader(_))
+    ));
+}
+
+#[test]
+fn matching_bind_groups() {
+    use wgpu::ShaderStages as Stages;
+    // The storage buffer of group 2 isn't used, so it needs no layout
+    assert_eq!(
+        check_bind_groups(
+            &[entry(0, Stages::VERTEX, UNIFORM)],
+            &[
+                entry(0, Stages::FRAGMENT, TEXTURE),
+                entry(1, Stages::VERTEX_FRAGMENT, SAMPLER),
+            ],
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn bind_group_mismatches() {
+    use wgpu::ShaderStages as Stages;
+    assert_eq!(
+        check_bind_groups(
+            &[entry(0, Stages::VERTEX, UNIFORM)],
+            &[entry(0, Stages::FRAGMENT, TEXTURE)],
+        ),
+        Err(ReflectionError::MissingBinding {
+            group: 1,
+            binding: 1
+        })
+    );
+
+    let storage = wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Storage { read_only: true },
+        has_dynamic_offset: false,
+        min_binding_size: None,
+    };
+    let error = check_bind_groups(
+        &[entry(0, Stages::VERTEX, storage)],
+        &[
+            entry(0, Stages::FRAGMENT, TEXTURE),
+            entry(1, Stages::FRAGMENT, SAMPLER),
+        ],
+    )
+    .unwrap_err();
+    assert_eq!(
+        error,
+        ReflectionError::BindingMismatch {
+            group: 0,
+            binding: 0,
+            shader_type: "uniform Camera".to_string(),
+        }
+    );
+
+    let depth = wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison);
+    assert!(matches!(
+        check_bind_groups(
+            &[entry(0, Stages::VERTEX, UNIFORM)],
+            &[
+                entry(0, Stages::FRAGMENT, TEXTURE),
+                entry(1, Stages::FRAGMENT, depth),
+            ],
+        ),
+        Err(ReflectionError::BindingMismatch {
+            group: 1,
+            binding: 1,
+            ..
+        })
+    ));
+
+    assert_eq!(
+        check_bind_groups(
+            &[entry(0, Stages::FRAGMENT, UNIFORM)],
+            &[
+                entry(0, Stages::FRAGMENT, TEXTURE),
+                entry(1, Stages::FRAGMENT, SAMPLER),
+            ],
+        ),
+        Err(ReflectionError::NotVisible {
+            group: 0,
+            binding: 0,
+            stage: Stages::VERTEX,
+        })
+    );
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray, WgslStruct)]
+#[wgsl(vertex)]
+struct Vertex {
+    position: [f32; 2],
+    color: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(WgslStruct)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+}
+
+#[test]
+fn generated_declarations() {
+    let source = WgslPreprocessor::new()
+        .with_struct::<Vertex>()
+        .with_struct::<CameraUniform>()
+        .process(
+            "#include \"CameraUniform\"\n\
+             #include \"Vertex\"\n\
+             @group(0) @binding(0) var<uniform> camera: CameraUniform;\n\
+             @vertex\n\
+             fn vs_main(vertex: Vertex) -> @builtin(position) vec4<f32> {\n\
+             \x20   return camera.view_proj * vec4<f32>(vertex.position, vertex.color.r, 1.0);\n\
+             }",
+        )
+        .unwrap();
+    let shader = ShaderReflection::parse(&source).unwrap();
+    assert_eq!(
+        shader.check_vertex_layouts("vs_main", &[Vertex::desc()]),
+        Ok(())
+    );
+    let camera = [entry(0, wgpu::ShaderStages::VERTEX, UNIFORM)];
+    assert_eq!(
+        shader.check_bind_groups(
+            &["vs_main"],
+            &[wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &camera,
+            }],
+        ),
+        Ok(())
+    );
+}
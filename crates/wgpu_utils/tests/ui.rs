@@ -0,0 +1,7 @@
+/// Checks the errors of misused derives, see tests/ui for the expected messages.
+/// Run with TRYBUILD=overwrite to update them after changing a message.
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,15 @@
+use wgpu_utils::Bindable;
+
+#[derive(Bindable)]
+struct Bindings<'a> {
+    #[uniform]
+    camera: &'a wgpu::Buffer,
+    #[storage(read_only)]
+    palette: &'a wgpu::Buffer,
+    #[sampler(binding = 1)]
+    sampler: &'a wgpu::Sampler,
+    // Fields need a resource attribute
+    count: u32,
+}
+
+fn main() {}
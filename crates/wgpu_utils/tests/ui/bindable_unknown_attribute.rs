@@ -0,0 +1,14 @@
+use wgpu_utils::Bindable;
+
+#[derive(Bindable)]
+struct Bindings<'a> {
+    #[uniform(read_only)]
+    camera: &'a wgpu::Buffer,
+    #[texture(sample_type = "half", visibility = "fragment | geometry")]
+    noise: &'a wgpu::TextureView,
+    #[sampler]
+    #[texture]
+    sampler: &'a wgpu::Sampler,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use wgpu_utils::Bindable;
+
+#[derive(Bindable)]
+struct Bindings<'a> {
+    #[texture(dimension = "D5")]
+    volume: &'a wgpu::TextureView,
+}
+
+fn main() {}
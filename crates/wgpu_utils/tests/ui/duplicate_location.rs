@@ -0,0 +1,11 @@
+use wgpu_utils::VertexAttributeArray;
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct Vertex {
+    position: [f32; 2],
+    #[vertex(location = 0)]
+    color: [f32; 3],
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use wgpu_utils::VertexAttributeArray;
+
+#[derive(VertexAttributeArray)]
+enum Vertex {
+    Point([f32; 2]),
+}
+
+fn main() {}
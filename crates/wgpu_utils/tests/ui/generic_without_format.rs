@@ -0,0 +1,17 @@
+use wgpu_utils::{VertexAttributeArray};
+
+struct Position {
+    x: f32,
+    y: f32,
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct Vertex<T> {
+    position: T,
+    color: [f32; 3],
+}
+
+fn main() {
+    let _ = Vertex::<Position>::desc();
+}
@@ -0,0 +1,12 @@
+use wgpu_utils::VertexAttributeArray;
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct Vertex {
+    #[vertex(location = "one")]
+    position: [f32; 2],
+    #[vertex(location = -1)]
+    color: [f32; 3],
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use wgpu_utils::VertexAttributeArray;
+
+const COLUMNS: usize = 4;
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+#[vertex(instance)]
+struct Instance {
+    model: [[f32; 4]; COLUMNS],
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use wgpu_utils::UniformLayout;
+
+#[repr(C)]
+#[derive(UniformLayout)]
+struct Light {
+    intensity: f32,
+    // WGSL aligns vec4 to 16 bytes
+    color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(UniformLayout)]
+struct Viewport {
+    // Needs padding up to 16 bytes
+    size: [f32; 2],
+}
+
+fn main() {}
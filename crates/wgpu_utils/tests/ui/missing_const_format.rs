@@ -0,0 +1,15 @@
+use wgpu_utils::{VertexAttributeArray};
+
+struct Position {
+    x: f32,
+    y: f32,
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct Vertex {
+    position: Position,
+    color: [f32; 3],
+}
+
+fn main() {}
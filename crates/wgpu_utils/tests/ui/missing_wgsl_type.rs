@@ -0,0 +1,16 @@
+use wgpu_utils::WgslStruct;
+
+struct Color {
+    r: f32,
+    g: f32,
+    b: f32,
+}
+
+#[repr(C)]
+#[derive(WgslStruct)]
+struct Uniform {
+    color: Color,
+    alpha: f32,
+}
+
+fn main() {}
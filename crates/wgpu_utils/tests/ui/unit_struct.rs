@@ -0,0 +1,6 @@
+use wgpu_utils::VertexAttributeArray;
+
+#[derive(VertexAttributeArray)]
+struct Empty;
+
+fn main() {}
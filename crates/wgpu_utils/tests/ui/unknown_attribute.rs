@@ -0,0 +1,12 @@
+use wgpu_utils::VertexAttributeArray;
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+#[vertex(stride = 32)]
+struct Vertex {
+    position: [f32; 2],
+    #[vertex(offset = 8)]
+    color: [f32; 3],
+}
+
+fn main() {}
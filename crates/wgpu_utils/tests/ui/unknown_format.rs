@@ -0,0 +1,11 @@
+use wgpu_utils::{VertexAttributeArray};
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct Vertex {
+    position: [f32; 2],
+    #[vertex(format = "Float32x9")]
+    color: [f32; 3],
+}
+
+fn main() {}
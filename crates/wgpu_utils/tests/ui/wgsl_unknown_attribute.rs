@@ -0,0 +1,11 @@
+use wgpu_utils::WgslStruct;
+
+#[repr(C)]
+#[derive(WgslStruct)]
+#[wgsl(storage)]
+struct Uniform {
+    #[wgsl(name = "scale")]
+    size: f32,
+}
+
+fn main() {}
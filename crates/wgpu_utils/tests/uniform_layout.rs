@@ -0,0 +1,110 @@
+// Fields are only read through the generated layouts
+#![allow(dead_code)]
+
+use std::mem::{offset_of, size_of};
+
+use wgpu_utils::wgsl::{check_uniform_layout, Member};
+use wgpu_utils::{UniformLayout, WgslStruct, WgslType};
+
+/// Mixed scalars and vectors, padded as WGSL lays them out in uniforms
+#[repr(C)]
+#[derive(UniformLayout, WgslStruct)]
+struct Light {
+    color: [f32; 4],
+    direction: [f32; 3],
+    intensity: f32,
+    position: [f32; 2],
+    range: f32,
+    _padding: f32,
+}
+
+#[repr(C)]
+#[derive(UniformLayout)]
+struct Material<T> {
+    tint: T,
+    roughness: f32,
+    metallic: f32,
+}
+
+fn members<T: UniformLayout>() -> Vec<(&'static str, &'static str, u64)> {
+    T::MEMBERS
+        .iter()
+        .map(|member| (member.name, member.wgsl, member.offset))
+        .collect()
+}
+
+#[test]
+fn members_at_rust_offsets() {
+    // Checked where it's declared, using it again is a no-op
+    let () = Light::CHECK;
+    assert_eq!(
+        members::<Light>(),
+        [
+            ("color", "vec4<f32>", 0),
+            ("direction", "vec3<f32>", 16),
+            ("intensity", "f32", 28),
+            ("position", "vec2<f32>", 32),
+            ("range", "f32", 40),
+            ("_padding", "f32", 44),
+        ]
+    );
+    // The same layout is declared for the shaders
+    assert!(Light::wgsl().contains("    color: vec4<f32>,\n"));
+}
+
+#[test]
+fn generic_uniform() {
+    let () = Material::<[f32; 2]>::CHECK;
+    assert_eq!(
+        members::<Material<[f32; 2]>>(),
+        [
+            ("tint", "vec2<f32>", 0),
+            ("roughness", "f32", 8),
+            ("metallic", "f32", 12),
+        ]
+    );
+}
+
+fn member<T: WgslType>(name: &'static str, offset: usize) -> Member {
+    Member::of::<T>(name, offset)
+}
+
+#[test]
+fn padded_members() {
+    // Padding the Rust struct adds before a member is fine, WGSL gets a @size
+    check_uniform_layout(
+        &[member::<f32>("scale", 0), member::<[f32; 2]>("offset", 16)],
+        32,
+    );
+}
+
+#[test]
+#[should_panic(expected = "placed before where WGSL aligns it")]
+fn misaligned_vector() {
+    #[repr(C)]
+    struct Unpadded {
+        intensity: f32,
+        color: [f32; 3],
+    }
+    check_uniform_layout(
+        &[
+            member::<f32>("intensity", offset_of!(Unpadded, intensity)),
+            member::<[f32; 3]>("color", offset_of!(Unpadded, color)),
+        ],
+        size_of::<Unpadded>(),
+    );
+}
+
+#[test]
+#[should_panic(expected = "smaller than WGSL needs")]
+fn vec3_tail_padding() {
+    // A vec3 is 16 byte aligned, so the struct is 16 bytes in WGSL
+    check_uniform_layout(&[member::<[f32; 3]>("normal", 0)], 12);
+}
+
+#[test]
+#[should_panic(expected = "padded to a multiple of 16 bytes")]
+fn struct_alignment() {
+    // Fine for WGSL on its own, but not nested in other uniforms
+    check_uniform_layout(&[member::<[f32; 2]>("viewport", 0)], 8);
+}
@@ -0,0 +1,547 @@
+// Vertex fields are only read through the generated layouts
+#![allow(dead_code)]
+
+use internals::ConstFormat;
+use wgpu::VertexFormat::*;
+use wgpu_utils::{Snorm, Unorm, VertexAttributeArray, F16};
+
+/// Checks a derived layout against a hand computed stride and (format, offset)
+/// per field, with shader locations following the field order
+fn assert_layout<T: VertexAttributeArray>(stride: u64, expected: &[(wgpu::VertexFormat, u64)]) {
+    let layout = T::desc();
+    assert_eq!(layout.array_stride, stride);
+    assert_eq!(layout.step_mode, wgpu::VertexStepMode::Vertex);
+    let expected: Vec<wgpu::VertexAttribute> = expected
+        .iter()
+        .enumerate()
+        .map(|(location, &(format, offset))| wgpu::VertexAttribute {
+            format,
+            offset,
+            shader_location: location as u32,
+        })
+        .collect();
+    assert_eq!(layout.attributes, expected.as_slice());
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct OneField {
+    value: f32,
+}
+
+#[test]
+fn one_field() {
+    assert_layout::<OneField>(4, &[(Float32, 0)]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct TwoFields {
+    position: [f32; 2],
+    color: [f32; 3],
+}
+
+#[test]
+fn two_fields() {
+    assert_layout::<TwoFields>(20, &[(Float32x2, 0), (Float32x3, 8)]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct ThreeFields {
+    position: [f32; 3],
+    color: [f32; 4],
+    uv: [f32; 2],
+}
+
+#[test]
+fn three_fields() {
+    assert_layout::<ThreeFields>(36, &[(Float32x3, 0), (Float32x4, 12), (Float32x2, 28)]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct FourFields {
+    position: [f32; 3],
+    normal: [f32; 3],
+    uv: [f32; 2],
+    id: u32,
+}
+
+#[test]
+fn four_fields() {
+    assert_layout::<FourFields>(
+        36,
+        &[
+            (Float32x3, 0),
+            (Float32x3, 12),
+            (Float32x2, 24),
+            (Uint32, 32),
+        ],
+    );
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct FiveFields {
+    a: f32,
+    b: [f32; 2],
+    c: [f32; 3],
+    d: [f32; 4],
+    e: u32,
+}
+
+#[test]
+fn five_fields() {
+    assert_layout::<FiveFields>(
+        44,
+        &[
+            (Float32, 0),
+            (Float32x2, 4),
+            (Float32x3, 12),
+            (Float32x4, 24),
+            (Uint32, 40),
+        ],
+    );
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct SixFields {
+    a: [u32; 2],
+    b: [u32; 3],
+    c: u32,
+    d: [f32; 4],
+    e: f32,
+    f: [f32; 2],
+}
+
+#[test]
+fn six_fields() {
+    assert_layout::<SixFields>(
+        52,
+        &[
+            (Uint32x2, 0),
+            (Uint32x3, 8),
+            (Uint32, 20),
+            (Float32x4, 24),
+            (Float32, 40),
+            (Float32x2, 44),
+        ],
+    );
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct SevenFields {
+    model_x: [f32; 4],
+    model_y: [f32; 4],
+    model_z: [f32; 4],
+    model_w: [f32; 4],
+    color: [f32; 3],
+    alpha: f32,
+    ids: [u32; 2],
+}
+
+#[test]
+fn seven_fields() {
+    assert_layout::<SevenFields>(
+        88,
+        &[
+            (Float32x4, 0),
+            (Float32x4, 16),
+            (Float32x4, 32),
+            (Float32x4, 48),
+            (Float32x3, 64),
+            (Float32, 76),
+            (Uint32x2, 80),
+        ],
+    );
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct EightFields {
+    a: f32,
+    b: u32,
+    c: [f32; 2],
+    d: [u32; 2],
+    e: [f32; 3],
+    f: [u32; 3],
+    g: [f32; 4],
+    h: f32,
+}
+
+#[test]
+fn eight_fields() {
+    assert_layout::<EightFields>(
+        68,
+        &[
+            (Float32, 0),
+            (Uint32, 4),
+            (Float32x2, 8),
+            (Uint32x2, 16),
+            (Float32x3, 24),
+            (Uint32x3, 36),
+            (Float32x4, 48),
+            (Float32, 64),
+        ],
+    );
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct TupleFields([f32; 2], [f32; 3], u32);
+
+#[test]
+fn tuple_fields() {
+    assert_layout::<TupleFields>(24, &[(Float32x2, 0), (Float32x3, 8), (Uint32, 20)]);
+}
+
+/// A vec3 aligned like a WGSL vec3<f32> in a uniform
+#[repr(C, align(16))]
+struct AlignedVec3([f32; 3]);
+
+impl ConstFormat for AlignedVec3 {
+    const FORMAT: wgpu::VertexFormat = Float32x3;
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct PaddedFields {
+    id: u32,
+    normal: AlignedVec3,
+    weight: f32,
+}
+
+#[test]
+fn padding_between_fields() {
+    assert_layout::<PaddedFields>(48, &[(Uint32, 0), (Float32x3, 16), (Float32, 32)]);
+}
+
+/// Checks a derived layout against hand computed (format, offset, location)
+/// per attribute
+fn assert_attributes<T: VertexAttributeArray>(expected: &[(wgpu::VertexFormat, u64, u32)]) {
+    let expected: Vec<wgpu::VertexAttribute> = expected
+        .iter()
+        .map(|&(format, offset, shader_location)| wgpu::VertexAttribute {
+            format,
+            offset,
+            shader_location,
+        })
+        .collect();
+    assert_eq!(T::desc().attributes, expected.as_slice());
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+#[vertex(start_location = 3)]
+struct StartLocation {
+    position: [f32; 2],
+    color: [f32; 3],
+}
+
+#[test]
+fn start_location() {
+    assert_attributes::<StartLocation>(&[(Float32x2, 0, 3), (Float32x3, 8, 4)]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct ExplicitLocations {
+    #[vertex(location = 4)]
+    position: [f32; 2],
+    uv: [f32; 2],
+    #[vertex(location = 1)]
+    color: [f32; 3],
+}
+
+#[test]
+fn explicit_locations() {
+    assert_attributes::<ExplicitLocations>(&[
+        (Float32x2, 0, 4),
+        (Float32x2, 8, 5),
+        (Float32x3, 16, 1),
+    ]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct FormatOverrides {
+    position: [f32; 2],
+    #[vertex(format = "Unorm8x4")]
+    color: u32,
+    #[vertex(format = "Sint32x2")]
+    cell: [u32; 2],
+}
+
+#[test]
+fn format_overrides() {
+    assert_attributes::<FormatOverrides>(&[(Float32x2, 0, 0), (Unorm8x4, 8, 1), (Sint32x2, 12, 2)]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+#[vertex(start_location = 2)]
+struct SkippedFields {
+    #[vertex(skip)]
+    id: u32,
+    position: [f32; 3],
+    #[vertex(skip)]
+    flags: u32,
+    #[vertex(location = 6, format = "Snorm16x2")]
+    normal: u32,
+}
+
+#[test]
+fn skipped_fields() {
+    // Skipped fields still take their space in the stride
+    assert_eq!(SkippedFields::desc().array_stride, 24);
+    assert_attributes::<SkippedFields>(&[(Float32x3, 4, 2), (Snorm16x2, 20, 6)]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+#[vertex(instance, start_location = 2)]
+struct ModelInstance {
+    model: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+#[test]
+fn instance_matrix() {
+    let layout = ModelInstance::desc();
+    assert_eq!(layout.step_mode, wgpu::VertexStepMode::Instance);
+    assert_eq!(layout.array_stride, 80);
+    assert_attributes::<ModelInstance>(&[
+        (Float32x4, 0, 2),
+        (Float32x4, 16, 3),
+        (Float32x4, 32, 4),
+        (Float32x4, 48, 5),
+        (Float32x4, 64, 6),
+    ]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct SmallMatrices {
+    id: u32,
+    #[vertex(location = 4)]
+    normal_matrix: [[f32; 3]; 3],
+    uv_matrix: [[f32; 2]; 2],
+}
+
+#[test]
+fn matrix_columns() {
+    assert_eq!(
+        SmallMatrices::desc().step_mode,
+        wgpu::VertexStepMode::Vertex
+    );
+    assert_attributes::<SmallMatrices>(&[
+        (Uint32, 0, 0),
+        (Float32x3, 4, 4),
+        (Float32x3, 16, 5),
+        (Float32x3, 28, 6),
+        (Float32x2, 40, 7),
+        (Float32x2, 48, 8),
+    ]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct GenericPosition<T> {
+    position: T,
+    color: [f32; 3],
+}
+
+#[test]
+fn generic_fields() {
+    assert_layout::<GenericPosition<[f32; 2]>>(20, &[(Float32x2, 0), (Float32x3, 8)]);
+    assert_layout::<GenericPosition<[f32; 4]>>(28, &[(Float32x4, 0), (Float32x3, 16)]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct GenericTuple<A, B>(A, B)
+where
+    A: Copy;
+
+#[test]
+fn generic_tuple_fields() {
+    assert_layout::<GenericTuple<f32, [u32; 2]>>(12, &[(Float32, 0), (Uint32x2, 4)]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct ConstGeneric<const N: usize> {
+    id: u32,
+    weights: [f32; N],
+}
+
+#[test]
+fn const_generic_fields() {
+    assert_layout::<ConstGeneric<3>>(16, &[(Uint32, 0), (Float32x3, 4)]);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+#[vertex(instance)]
+struct BorrowedInstance<'a, T> {
+    model: [[f32; 4]; 4],
+    tint: T,
+    #[vertex(skip)]
+    marker: std::marker::PhantomData<&'a ()>,
+}
+
+#[test]
+fn lifetime_and_matrix_fields() {
+    let layout = BorrowedInstance::<[f32; 3]>::desc();
+    assert_eq!(layout.step_mode, wgpu::VertexStepMode::Instance);
+    assert_attributes::<BorrowedInstance<[f32; 3]>>(&[
+        (Float32x4, 0, 0),
+        (Float32x4, 16, 1),
+        (Float32x4, 32, 2),
+        (Float32x4, 48, 3),
+        (Float32x3, 64, 4),
+    ]);
+}
+
+#[test]
+fn layout_is_const() {
+    const LAYOUT: wgpu::VertexBufferLayout<'static> = TwoFields::LAYOUT;
+    assert_eq!(LAYOUT.attributes.len(), 2);
+    assert_eq!(LAYOUT.array_stride, 20);
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct SignedAndWideFields {
+    cell: i32,
+    offset: [i32; 2],
+    normal: [i32; 3],
+    bones: [u32; 4],
+    weights: [i32; 4],
+    time: f64,
+    origin: [f64; 3],
+}
+
+#[test]
+fn signed_and_wide_fields() {
+    assert_layout::<SignedAndWideFields>(
+        88,
+        &[
+            (Sint32, 0),
+            (Sint32x2, 4),
+            (Sint32x3, 12),
+            (Uint32x4, 24),
+            (Sint32x4, 40),
+            (Float64, 56),
+            (Float64x3, 64),
+        ],
+    );
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct PackedFields {
+    ids: [u8; 4],
+    offset: [i8; 2],
+    color: Unorm<[u8; 4]>,
+    tangent: Snorm<[i8; 2]>,
+    uv: Unorm<[u16; 2]>,
+    normal: Snorm<[i16; 4]>,
+    cells: [u16; 2],
+    delta: [i16; 4],
+    position: [F16; 2],
+    tint: [F16; 4],
+    emission: wgpu_utils::Unorm10_10_10_2,
+}
+
+#[test]
+fn packed_fields() {
+    assert_layout::<PackedFields>(
+        52,
+        &[
+            (Uint8x4, 0),
+            (Sint8x2, 4),
+            (Unorm8x4, 6),
+            (Snorm8x2, 10),
+            (Unorm16x2, 12),
+            (Snorm16x4, 16),
+            (Uint16x2, 24),
+            (Sint16x4, 28),
+            (Float16x2, 36),
+            (Float16x4, 40),
+            (Unorm10_10_10_2, 48),
+        ],
+    );
+}
+
+#[test]
+fn half_floats() {
+    let bits = |value: f32| F16::from_f32(value).0;
+    assert_eq!(bits(0.0), 0x0000);
+    assert_eq!(bits(-0.0), 0x8000);
+    assert_eq!(bits(1.0), 0x3c00);
+    assert_eq!(bits(-2.0), 0xc000);
+    assert_eq!(bits(0.1), 0x2e66);
+    assert_eq!(bits(65504.0), 0x7bff);
+    // Rounds to even between 2048 and 2050
+    assert_eq!(bits(2049.0), 0x6800);
+    assert_eq!(bits(1.0e6), 0x7c00);
+    assert_eq!(bits(f32::NEG_INFINITY), 0xfc00);
+    assert_eq!(bits(f32::NAN) & 0x7e00, 0x7e00);
+    // Smallest normal and subnormals
+    assert_eq!(bits(2.0f32.powi(-14)), 0x0400);
+    assert_eq!(bits(2.0f32.powi(-24)), 0x0001);
+    assert_eq!(bits(1.0e-8), 0x0000);
+}
+
+#[cfg(feature = "glam")]
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct GlamFields {
+    position: glam::Vec3,
+    uv: glam::Vec2,
+    color: glam::Vec4,
+    cell: glam::IVec2,
+    ids: glam::UVec3,
+}
+
+#[cfg(feature = "glam")]
+#[test]
+fn glam_fields() {
+    // Vec4 is 16 byte aligned when SIMD is used
+    let color = std::mem::offset_of!(GlamFields, color) as u64;
+    assert_attributes::<GlamFields>(&[
+        (Float32x3, 0, 0),
+        (Float32x2, 12, 1),
+        (Float32x4, color, 2),
+        (Sint32x2, color + 16, 3),
+        (Uint32x3, color + 24, 4),
+    ]);
+}
+
+#[cfg(feature = "mint")]
+#[repr(C)]
+#[derive(VertexAttributeArray)]
+struct MintFields {
+    position: mint::Point3<f32>,
+    normal: mint::Vector3<f32>,
+    cell: mint::Vector2<i32>,
+    ids: mint::Vector4<u32>,
+}
+
+#[cfg(feature = "mint")]
+#[test]
+fn mint_fields() {
+    assert_layout::<MintFields>(
+        48,
+        &[
+            (Float32x3, 0),
+            (Float32x3, 12),
+            (Sint32x2, 24),
+            (Uint32x4, 32),
+        ],
+    );
+}
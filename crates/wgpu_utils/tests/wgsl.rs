@@ -0,0 +1,255 @@
+// Fields are only read through the generated declarations
+#![allow(dead_code)]
+
+use wgpu_utils::wgsl::{PreprocessError, WgslPreprocessor};
+use wgpu_utils::{VertexAttributeArray, WgslStruct};
+
+#[repr(C)]
+#[derive(VertexAttributeArray, WgslStruct)]
+#[wgsl(vertex)]
+struct Vertex {
+    position: [f32; 2],
+    color: [f32; 3],
+}
+
+#[test]
+fn vertex_input() {
+    assert_eq!(Vertex::NAME, "Vertex");
+    assert_eq!(
+        Vertex::wgsl(),
+        "struct Vertex {\n\
+        \x20   @location(0) position: vec2<f32>,\n\
+        \x20   @location(1) color: vec3<f32>,\n\
+        };\n"
+    );
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray, WgslStruct)]
+#[vertex(instance, start_location = 2)]
+#[wgsl(vertex)]
+struct Instance {
+    model: [[f32; 4]; 2],
+    #[vertex(skip)]
+    id: u32,
+    #[vertex(format = "Unorm8x4")]
+    tint: u32,
+    cell: [i32; 2],
+}
+
+#[test]
+fn instance_input() {
+    assert_eq!(
+        Instance::wgsl(),
+        "struct Instance {\n\
+        \x20   @location(2) model_0: vec4<f32>,\n\
+        \x20   @location(3) model_1: vec4<f32>,\n\
+        \x20   @location(4) tint: vec4<f32>,\n\
+        \x20   @location(5) cell: vec2<i32>,\n\
+        };\n"
+    );
+}
+
+#[repr(C)]
+#[derive(VertexAttributeArray, WgslStruct)]
+#[wgsl(vertex)]
+struct TupleVertex([f32; 3], u32);
+
+#[test]
+fn tuple_input() {
+    assert_eq!(
+        TupleVertex::wgsl(),
+        "struct TupleVertex {\n\
+        \x20   @location(0) field_0: vec3<f32>,\n\
+        \x20   @location(1) field_1: u32,\n\
+        };\n"
+    );
+}
+
+#[repr(C)]
+#[derive(WgslStruct)]
+struct LineUniform {
+    viewport: [f32; 2],
+    half_width: f32,
+    _padding: f32,
+}
+
+#[test]
+fn uniform() {
+    assert_eq!(
+        LineUniform::wgsl(),
+        "struct LineUniform {\n\
+        \x20   viewport: vec2<f32>,\n\
+        \x20   half_width: f32,\n\
+        \x20   _padding: f32,\n\
+        };\n"
+    );
+}
+
+/// A vec3 aligned like a WGSL vec3<f32>, which Rust rounds up to 16 bytes
+#[repr(C, align(16))]
+struct AlignedVec3([f32; 3]);
+
+impl wgpu_utils::WgslType for AlignedVec3 {
+    const WGSL: &'static str = "vec3<f32>";
+    const ALIGN: u64 = 16;
+    const SIZE: u64 = 12;
+}
+
+#[repr(C)]
+#[derive(WgslStruct)]
+struct PaddedMember {
+    position: AlignedVec3,
+    weight: f32,
+}
+
+#[repr(C, align(32))]
+#[derive(WgslStruct)]
+struct PaddedEnd {
+    color: [f32; 4],
+}
+
+#[test]
+fn uniform_padding() {
+    // WGSL would place weight in the last 4 bytes of the vec3
+    assert_eq!(
+        PaddedMember::wgsl(),
+        "struct PaddedMember {\n\
+        \x20   @size(16) position: vec3<f32>,\n\
+        \x20   weight: f32,\n\
+        };\n"
+    );
+    assert_eq!(
+        PaddedEnd::wgsl(),
+        "struct PaddedEnd {\n\
+        \x20   @size(32) color: vec4<f32>,\n\
+        };\n"
+    );
+}
+
+#[repr(C)]
+#[derive(WgslStruct)]
+struct Misaligned {
+    scale: f32,
+    normal: [f32; 3],
+}
+
+#[test]
+#[should_panic(
+    expected = "Misaligned.normal is at offset 4 but WGSL places a vec3<f32> at offset 16"
+)]
+fn misaligned_uniform() {
+    Misaligned::wgsl();
+}
+
+#[repr(C)]
+#[derive(WgslStruct)]
+struct TooSmall {
+    normal: [f32; 3],
+}
+
+#[test]
+#[should_panic(expected = "TooSmall is 12 bytes but WGSL needs 16 bytes")]
+fn uniform_too_small() {
+    TooSmall::wgsl();
+}
+
+#[cfg(feature = "glam")]
+#[repr(C)]
+#[derive(WgslStruct)]
+struct CameraUniform {
+    view_proj: glam::Mat4,
+    position: glam::Vec3,
+    exposure: f32,
+}
+
+#[cfg(feature = "glam")]
+#[test]
+fn glam_uniform() {
+    assert_eq!(
+        CameraUniform::wgsl(),
+        "struct CameraUniform {\n\
+        \x20   view_proj: mat4x4<f32>,\n\
+        \x20   position: vec3<f32>,\n\
+        \x20   exposure: f32,\n\
+        };\n"
+    );
+}
+
+#[test]
+fn includes() {
+    let preprocessor = WgslPreprocessor::new()
+        .with_struct::<Vertex>()
+        .with_struct::<LineUniform>()
+        .with_snippet(
+            "common",
+            "#include \"LineUniform\"\nconst SCALE: f32 = 2.0;",
+        );
+    let source = "#include \"common\"\n\
+        // #include \"Unknown\" is left as is in comments\n\
+        \x20 #include \"Vertex\"\n\
+        #include \"LineUniform\"\n\
+        @vertex fn vs_main(vertex: Vertex) {}";
+    assert_eq!(
+        preprocessor.process(source).unwrap(),
+        format!(
+            "{}const SCALE: f32 = 2.0;\n\
+            // #include \"Unknown\" is left as is in comments\n\
+            {}@vertex fn vs_main(vertex: Vertex) {{}}\n",
+            LineUniform::wgsl(),
+            Vertex::wgsl()
+        )
+    );
+}
+
+#[test]
+fn include_errors() {
+    let preprocessor = WgslPreprocessor::new()
+        .with_snippet("nested", "\n#include \"Missing\"")
+        .with_snippet("invalid", "#include Vertex");
+    // Lines are counted in the snippet the include is in
+    let error = preprocessor.process("\n\n#include \"nested\"").unwrap_err();
+    assert_eq!(
+        error,
+        PreprocessError::UnknownInclude {
+            name: "Missing".to_string(),
+            line: 2,
+            snippet: Some("nested".to_string()),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "unknown include \"Missing\" at line 2 of \"nested\""
+    );
+    assert_eq!(
+        preprocessor.process("#include \"invalid\""),
+        Err(PreprocessError::InvalidInclude {
+            line: 1,
+            snippet: Some("invalid".to_string()),
+        })
+    );
+    let error = preprocessor.process("\n#include \"Other\"").unwrap_err();
+    assert_eq!(
+        error,
+        PreprocessError::UnknownInclude {
+            name: "Other".to_string(),
+            line: 2,
+            snippet: None,
+        }
+    );
+    assert_eq!(error.to_string(), "unknown include \"Other\" at line 2");
+    for source in [
+        "#include",
+        "#include Vertex",
+        "#include \"\"",
+        "#include \"Vertex",
+    ] {
+        assert_eq!(
+            preprocessor.process(&format!("\n{source}")),
+            Err(PreprocessError::InvalidInclude {
+                line: 2,
+                snippet: None,
+            })
+        );
+    }
+}
@@ -1,15 +1,22 @@
+use std::collections::HashSet;
+
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned, ToTokens};
+use syn::ext::IdentExt;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
 use syn::{Data, Fields};
 
 /// Derives the ```VertexAttributeArray``` trait
 ///
 /// Ensures the struct is capable of generating a VertexBufferLayout
 /// by calling desc. Requires that each field implement
-/// ```ConstFormat``` trait
+/// ```ConstFormat``` trait. Attribute offsets are taken from the
+/// struct's layout, padding included, so it should be ```#[repr(C)]```
 ///
 /// Example
-/// ```
+/// ```ignore
 /// struct MyVec3{
 ///     x: f32,
 ///     y: f32,
@@ -18,6 +25,7 @@ use syn::{Data, Fields};
 ///
 /// const_format_of!(MyVec3 => wgpu::VertexFormat::Float32x3);
 ///
+/// #[repr(C)]
 /// #[derive(VertexAttributeArray)]
 /// struct Vertex {
 ///     pos: MyVec3
@@ -25,64 +33,877 @@ use syn::{Data, Fields};
 /// }
 /// ```
 ///
-#[proc_macro_derive(VertexAttributeArray)]
+/// Shader locations follow the field order, starting at 0. They can be changed with
+/// ```#[vertex(...)]``` attributes:
+/// - ```#[vertex(start_location = N)]``` on the struct, to start at N so it can be
+///   combined with other vertex buffers
+/// - ```#[vertex(location = N)]``` on a field, the following fields continue from N + 1
+/// - ```#[vertex(format = "Unorm8x4")]``` on a field, to use another wgpu::VertexFormat
+///   than the one of its type, such as packed formats
+/// - ```#[vertex(skip)]``` on a field, to leave it out of the layout
+///
+/// Fields that are arrays of arrays, such as ```[[f32; 4]; 4]```, are matrices taking
+/// a location per column. With ```#[vertex(instance)]``` on the struct the layout
+/// steps per instance instead of per vertex, for per instance data like model matrices.
+///
+/// Every wgpu::VertexFormat has a type implementing ```ConstFormat```, with the normalized
+/// and half float ones wrapped as in ```Unorm<[u8; 4]>```, ```Snorm<[i16; 2]>``` and
+/// ```[F16; 2]```. The ```glam``` and ```mint``` features implement it for their vectors.
+///
+/// Generic structs, tuple structs included, get a ```ConstFormat``` bound on every field
+/// type depending on their type or const parameters. Fields that can't have a format,
+/// like ```PhantomData```, need to be skipped.
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(VertexAttributeArray)]
+/// #[vertex(start_location = 2)]
+/// struct Instance {
+///     offset: [f32; 2], // @location(2)
+///     #[vertex(location = 5, format = "Unorm8x4")]
+///     color: u32, // @location(5)
+///     #[vertex(skip)]
+///     id: u32,
+/// }
+/// ```
+#[proc_macro_derive(VertexAttributeArray, attributes(vertex))]
 pub fn vertex_attribute_derive(input: TokenStream) -> TokenStream {
     // Construct a representation of Rust code as a syntax tree
     // that we can manipulate
-    let ast = syn::parse(input).unwrap();
+    let ast = syn::parse_macro_input!(input as syn::DeriveInput);
 
-    // Build the trait implementation
+    // Build the trait implementation, or the errors pointing at what's wrong
     impl_vertex_attribute(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
-fn impl_vertex_attribute(ast: &syn::DeriveInput) -> TokenStream {
+fn impl_vertex_attribute(ast: &syn::DeriveInput) -> syn::Result<TokenStream2> {
     let name = &ast.ident;
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+    // Field types depending on these need a ConstFormat bound
+    let generic_params = generic_params(&ast.generics);
+    let mut bounds: Vec<syn::WherePredicate> = Vec::new();
     // We pick each of the fields in our struct
-    let fields = match &ast.data {
+    let fields = struct_fields(ast, "VertexAttributeArray")?;
+
+    // Errors of the struct and every field are reported at once
+    let mut errors: Option<syn::Error> = None;
+    let mut report = |error: syn::Error| match &mut errors {
+        Some(errors) => errors.combine(error),
+        None => errors = Some(error),
+    };
+    let struct_options = struct_options(&ast.attrs).unwrap_or_else(|error| {
+        report(error);
+        StructOptions::default()
+    });
+    let mut location = struct_options.start_location;
+    let mut used_locations = HashSet::new();
+    let mut field_types = Vec::new();
+    // Generate the vertex attributes that we'll be inserting in our static array.
+    // Offsets come from offset_of!, so they account for the padding between fields.
+    for (i, f) in fields.iter().enumerate() {
+        let options = match field_options(&f.attrs) {
+            Ok(options) => options,
+            Err(error) => {
+                report(error);
+                continue;
+            }
+        };
+        if options.skip {
+            continue;
+        }
+        let member = match &f.ident {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(syn::Index::from(i)),
+        };
+        // Matrices take a location per column, as WGSL doesn't have matrix attributes
+        let (ty, columns) = match matrix_columns(&f.ty) {
+            Ok(Some((column, count))) => (column, count),
+            Ok(None) => (&f.ty, 1),
+            Err(error) => {
+                report(error);
+                continue;
+            }
+        };
+        let format = match options.format {
+            Some(format) => quote! { wgpu::VertexFormat::#format },
+            None => {
+                if mentions(ty.to_token_stream(), &generic_params) {
+                    bounds.push(syn::parse_quote! { #ty: ::wgpu_utils::ConstFormat });
+                }
+                // Spanning the field type, so a type without a format is reported
+                // where it's declared rather than somewhere in the generated code
+                quote_spanned! {ty.span()=> ::wgpu_utils::format_of::<#ty>() }
+            }
+        };
+        location = options.location.unwrap_or(location);
+        for column in 0..columns {
+            if !used_locations.insert(location) {
+                report(syn::Error::new_spanned(
+                    f,
+                    format!("shader location {location} is used by more than one field"),
+                ));
+            }
+            field_types.push(quote! {
+                wgpu::VertexAttribute {
+                    format: #format,
+                    offset: (::core::mem::offset_of!(#name #ty_generics, #member)
+                        + #column * ::core::mem::size_of::<#ty>()) as u64,
+                    shader_location: #location,
+                }
+            });
+            location += 1;
+        }
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+    let step_mode = if struct_options.instance {
+        quote! { wgpu::VertexStepMode::Instance }
+    } else {
+        quote! { wgpu::VertexStepMode::Vertex }
+    };
+
+    let mut generics = ast.generics.clone();
+    if !bounds.is_empty() {
+        generics.make_where_clause().predicates.extend(bounds);
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::wgpu_utils::VertexAttributeArray for #name #ty_generics #where_clause {
+            const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+                array_stride: ::core::mem::size_of::<Self>() as wgpu::BufferAddress,
+                step_mode: #step_mode,
+                attributes: &[#(#field_types),*],
+            };
+        }
+    })
+}
+
+/// The fields of a struct, or an error spanning what the derive doesn't support
+fn struct_fields<'a>(
+    ast: &'a syn::DeriveInput,
+    derive: &str,
+) -> syn::Result<&'a Punctuated<syn::Field, syn::Token![,]>> {
+    match &ast.data {
         Data::Struct(data) => match &data.fields {
-            Fields::Named(fields_named) => fields_named.named.clone(),
-            Fields::Unnamed(fields_unnamed) => fields_unnamed.unnamed.clone(),
-            _ => panic!("#[derive(VertexAttributeArray)] is not supported in unit structs"),
+            Fields::Named(fields_named) => Ok(&fields_named.named),
+            Fields::Unnamed(fields_unnamed) => Ok(&fields_unnamed.unnamed),
+            Fields::Unit => Err(syn::Error::new_spanned(
+                &ast.ident,
+                format!("#[derive({derive})] is not supported in unit structs"),
+            )),
         },
         // TODO: Add enums
-        _ => panic!("#[derive(VertexAttributeArray)] is only supported in structs"),
+        Data::Enum(data) => Err(syn::Error::new_spanned(
+            data.enum_token,
+            format!("#[derive({derive})] is only supported in structs"),
+        )),
+        Data::Union(data) => Err(syn::Error::new_spanned(
+            data.union_token,
+            format!("#[derive({derive})] is only supported in structs"),
+        )),
+    }
+}
+
+/// The type and const parameters of a struct, which field types may depend on
+fn generic_params(generics: &syn::Generics) -> Vec<&syn::Ident> {
+    generics
+        .type_params()
+        .map(|param| &param.ident)
+        .chain(generics.const_params().map(|param| &param.ident))
+        .collect()
+}
+
+/// Derives the ```WgslStruct``` trait
+///
+/// Generates the WGSL declaration of the struct, under its Rust name, so shaders can
+/// ```#include``` it through a ```WgslPreprocessor``` instead of declaring it by hand.
+/// By default it's declared for uniforms, requiring each field to implement the
+/// ```WgslType``` trait. Fields are checked against the WGSL alignment rules, and
+/// given a ```@size``` where the Rust struct has more padding, so it should be
+/// ```#[repr(C)]```.
+///
+/// With ```#[wgsl(vertex)]``` on the struct it's declared as a vertex shader input
+/// instead, with the locations and formats of its ```VertexAttributeArray``` layout.
+/// Matrix columns are named after their field, as in ```model_0```, and the fields
+/// of tuple structs after their index, as in ```field_0```.
+///
+/// Example
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(VertexAttributeArray, WgslStruct)]
+/// #[wgsl(vertex)]
+/// struct Vertex {
+///     position: [f32; 2],
+///     color: [f32; 3],
+/// }
+///
+/// // struct Vertex {
+/// //     @location(0) position: vec2<f32>,
+/// //     @location(1) color: vec3<f32>,
+/// // };
+/// let source: String = Vertex::wgsl();
+/// ```
+#[proc_macro_derive(WgslStruct, attributes(wgsl))]
+pub fn wgsl_struct_derive(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    impl_wgsl_struct(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn impl_wgsl_struct(ast: &syn::DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &ast.ident;
+    let wgsl_name = name.unraw().to_string();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+    let mut bounds: Vec<syn::WherePredicate> = Vec::new();
+    let fields = struct_fields(ast, "WgslStruct")?;
+
+    let mut errors: Option<syn::Error> = None;
+    let mut report = |error: syn::Error| match &mut errors {
+        Some(errors) => errors.combine(error),
+        None => errors = Some(error),
     };
+    let vertex = wgsl_options(&ast.attrs).unwrap_or_else(|error| {
+        report(error);
+        false
+    });
+    for attr in fields
+        .iter()
+        .flat_map(|f| &f.attrs)
+        .filter(|attr| attr.path().is_ident("wgsl"))
+    {
+        report(syn::Error::new_spanned(
+            attr,
+            "#[wgsl(...)] attributes are only supported on the struct",
+        ));
+    }
 
-    // Generate the vertex attributes iterator that we'll be inserting in our static array
-    let mut previous_type = None;
-    let field_types = fields.iter().enumerate().map(|(i, f)| {
-        let ty = &f.ty;
-        let offset = if let Some(prev) = previous_type {
-            quote! {size_of::<#prev>() as u64}
-        } else {
-            quote! { 0 as u64}
-        };
-        previous_type = Some(ty);
+    // Attribute names of a vertex input, or uniform members
+    let mut names = Vec::new();
+    let mut members = Vec::new();
+    if vertex {
+        for (i, f) in fields.iter().enumerate() {
+            // Errors in #[vertex(...)] attributes are reported by VertexAttributeArray
+            if field_options(&f.attrs).is_ok_and(|options| options.skip) {
+                continue;
+            }
+            let member_name = member_name(f, i);
+            match matrix_columns(&f.ty) {
+                Ok(Some((_, columns))) => {
+                    names.extend((0..columns).map(|column| format!("{member_name}_{column}")))
+                }
+                _ => names.push(member_name),
+            }
+        }
+    } else {
+        members = uniform_members(ast, fields, &mut bounds);
+    }
+    if names.is_empty() && members.is_empty() {
+        report(syn::Error::new_spanned(
+            name,
+            "#[derive(WgslStruct)] needs at least one field in the layout",
+        ));
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    let declaration = if vertex {
+        bounds.push(syn::parse_quote! { #name #ty_generics: ::wgpu_utils::VertexAttributeArray });
+        quote! {
+            ::wgpu_utils::wgsl::vertex_struct(
+                Self::NAME,
+                &[#(#names),*],
+                <Self as ::wgpu_utils::VertexAttributeArray>::LAYOUT.attributes,
+            )
+        }
+    } else {
         quote! {
-            wgpu::VertexAttribute {
-                format: format_of::<#ty>(),
-                offset: #offset,
-                shader_location: #i as u32,
+            ::wgpu_utils::wgsl::uniform_struct(
+                Self::NAME,
+                &[#(#members),*],
+                ::core::mem::size_of::<Self>(),
+            )
+        }
+    };
+
+    let mut generics = ast.generics.clone();
+    if !bounds.is_empty() {
+        generics.make_where_clause().predicates.extend(bounds);
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::wgpu_utils::WgslStruct for #name #ty_generics #where_clause {
+            const NAME: &'static str = #wgsl_name;
+
+            fn wgsl() -> ::std::string::String {
+                #declaration
             }
         }
-    });
+    })
+}
+
+/// Derives the ```Bindable``` trait
+///
+/// Generates the BindGroupLayout entries of a group of resources, and the bind group
+/// entries binding them, from a resource attribute on each field:
+/// - ```#[uniform]``` and ```#[storage]``` for buffers, ```#[storage(read_only)]```
+///   for storage buffers the shaders can't write
+/// - ```#[texture]``` for texture views, taking a ```sample_type``` of "float",
+///   "unfilterable_float", "depth", "sint" or "uint" (float by default), a
+///   ```dimension``` named after the wgpu::TextureViewDimension variant (D2 by
+///   default) and ```multisampled```
+/// - ```#[sampler]``` for samplers, with ```comparison``` or ```non_filtering```
+///   for other sampler binding types than filtering ones
+///
+/// Bindings follow the field order, starting at 0. Every resource attribute takes a
+/// ```binding = N```, the following fields continue from N + 1, and a ```visibility```
+/// listing the shader stages that can access it, as in "vertex | fragment" (the default).
+/// Buffer fields need an ```as_entire_binding``` method, so they're either a wgpu::Buffer
+/// or a reference to one, and texture and sampler fields deref to their wgpu type.
+///
+/// Example
+/// ```ignore
+/// #[derive(Bindable)]
+/// struct FrameBindings<'a> {
+///     #[uniform]
+///     camera: &'a wgpu::Buffer, // @binding(0)
+///     #[uniform(visibility = "fragment")]
+///     time: &'a wgpu::Buffer, // @binding(1)
+///     #[storage(read_only, binding = 4)]
+///     palette: &'a wgpu::Buffer, // @binding(4)
+///     #[texture(sample_type = "unfilterable_float", visibility = "fragment")]
+///     noise: &'a wgpu::TextureView, // @binding(5)
+/// }
+///
+/// let layout = device.create_bind_group_layout(&FrameBindings::layout_desc());
+/// let bind_group = FrameBindings { camera, time, palette, noise }
+///     .create_bind_group(&device, &layout, Some("frame_bind_group"));
+/// ```
+#[proc_macro_derive(Bindable, attributes(uniform, storage, texture, sampler))]
+pub fn bindable_derive(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    impl_bindable(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The attributes of Bindable fields, one per kind of resource
+const RESOURCES: [&str; 4] = ["uniform", "storage", "texture", "sampler"];
+
+fn impl_bindable(ast: &syn::DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &ast.ident;
+    let label = format!("{}_layout", snake_case(&name.unraw().to_string()));
+    let fields = struct_fields(ast, "Bindable")?;
+
+    let mut errors: Option<syn::Error> = None;
+    let mut report = |error: syn::Error| match &mut errors {
+        Some(errors) => errors.combine(error),
+        None => errors = Some(error),
+    };
+    let mut binding = 0;
+    let mut used_bindings = HashSet::new();
+    let mut layout_entries = Vec::new();
+    let mut entries = Vec::new();
+    for (i, f) in fields.iter().enumerate() {
+        let mut resources = f.attrs.iter().filter(|attr| {
+            RESOURCES
+                .iter()
+                .any(|resource| attr.path().is_ident(resource))
+        });
+        let Some(attr) = resources.next() else {
+            report(syn::Error::new_spanned(
+                f,
+                "#[derive(Bindable)] fields need one of #[uniform], #[storage], #[texture] or #[sampler]",
+            ));
+            continue;
+        };
+        if let Some(other) = resources.next() {
+            report(syn::Error::new_spanned(
+                other,
+                "#[derive(Bindable)] fields can only have one resource attribute",
+            ));
+            continue;
+        }
+        let options = match binding_options(attr) {
+            Ok(options) => options,
+            Err(error) => {
+                report(error);
+                continue;
+            }
+        };
+        binding = options.binding.unwrap_or(binding);
+        if !used_bindings.insert(binding) {
+            report(syn::Error::new_spanned(
+                f,
+                format!("binding {binding} is used by more than one field"),
+            ));
+        }
 
-    let attrs_array_len = field_types.len();
-
-    let gen = quote! {
-        impl VertexAttributeArray for #name {
-            fn desc() -> wgpu::VertexBufferLayout<'static> {
-                static ATTRIBUTES: [wgpu::VertexAttribute; #attrs_array_len] =  [
-                    #(#field_types),*
-                ];
-                wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &ATTRIBUTES,
+        let member = match &f.ident {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(syn::Index::from(i)),
+        };
+        let visibility = options.visibility;
+        let (ty, resource) = match options.resource {
+            Resource::Buffer(ty) => (
+                quote! {
+                    wgpu::BindingType::Buffer {
+                        ty: #ty,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    }
+                },
+                quote_spanned! {f.ty.span()=> self.#member.as_entire_binding() },
+            ),
+            Resource::Texture {
+                sample_type,
+                dimension,
+                multisampled,
+            } => (
+                quote! {
+                    wgpu::BindingType::Texture {
+                        sample_type: #sample_type,
+                        view_dimension: wgpu::TextureViewDimension::#dimension,
+                        multisampled: #multisampled,
+                    }
+                },
+                quote_spanned! {f.ty.span()=>
+                    wgpu::BindingResource::TextureView(&self.#member)
+                },
+            ),
+            Resource::Sampler(ty) => (
+                quote! { wgpu::BindingType::Sampler(#ty) },
+                quote_spanned! {f.ty.span()=>
+                    wgpu::BindingResource::Sampler(&self.#member)
+                },
+            ),
+        };
+        layout_entries.push(quote! {
+            wgpu::BindGroupLayoutEntry {
+                binding: #binding,
+                visibility: #visibility,
+                ty: #ty,
+                count: None,
+            }
+        });
+        entries.push(quote! {
+            wgpu::BindGroupEntry {
+                binding: #binding,
+                resource: #resource,
+            }
+        });
+        binding += 1;
+    }
+    if fields.is_empty() {
+        report(syn::Error::new_spanned(
+            name,
+            "#[derive(Bindable)] needs at least one field in the layout",
+        ));
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::wgpu_utils::Bindable for #name #ty_generics #where_clause {
+            const ENTRIES: &'static [wgpu::BindGroupLayoutEntry] = &[#(#layout_entries),*];
+
+            fn layout_desc<'layout>() -> wgpu::BindGroupLayoutDescriptor<'layout> {
+                wgpu::BindGroupLayoutDescriptor {
+                    label: Some(#label),
+                    entries: Self::ENTRIES,
                 }
             }
+
+            fn entries(&self) -> ::std::vec::Vec<wgpu::BindGroupEntry<'_>> {
+                ::std::vec![#(#entries),*]
+            }
         }
+    })
+}
+
+/// The kind of resource bound by a field, with its binding type
+enum Resource {
+    Buffer(TokenStream2),
+    Texture {
+        sample_type: TokenStream2,
+        dimension: syn::Ident,
+        multisampled: bool,
+    },
+    Sampler(TokenStream2),
+}
+
+/// Options of the field level #[uniform(...)], #[storage(...)], #[texture(...)]
+/// and #[sampler(...)] attributes
+struct BindingOptions {
+    resource: Resource,
+    binding: Option<u32>,
+    visibility: TokenStream2,
+}
+
+fn binding_options(attr: &syn::Attribute) -> syn::Result<BindingOptions> {
+    let kind = attr.path().get_ident().map(ToString::to_string);
+    let mut read_only = false;
+    let mut sample_type = quote! { wgpu::TextureSampleType::Float { filterable: true } };
+    let mut dimension = syn::Ident::new("D2", attr.span());
+    let mut multisampled = false;
+    let mut sampler = quote! { wgpu::SamplerBindingType::Filtering };
+    let mut binding = None;
+    let mut visibility = quote! { wgpu::ShaderStages::VERTEX_FRAGMENT };
+    // Bare attributes, such as #[uniform], take the defaults
+    if matches!(attr.meta, syn::Meta::List(_)) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("binding") {
+                let value: syn::LitInt = meta.value()?.parse()?;
+                binding = Some(value.base10_parse().map_err(|_| {
+                    syn::Error::new(value.span(), "bindings must be unsigned integers")
+                })?);
+                return Ok(());
+            }
+            if meta.path.is_ident("visibility") {
+                visibility = parse_visibility(meta.value()?.parse()?)?;
+                return Ok(());
+            }
+            match kind.as_deref() {
+                Some("storage") if meta.path.is_ident("read_only") => read_only = true,
+                Some("texture") if meta.path.is_ident("sample_type") => {
+                    sample_type = parse_sample_type(meta.value()?.parse()?)?
+                }
+                Some("texture") if meta.path.is_ident("dimension") => {
+                    // Named after the wgpu::TextureViewDimension variant, spanning the
+                    // literal so an unknown dimension is reported there
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    dimension = value.parse()?;
+                }
+                Some("texture") if meta.path.is_ident("multisampled") => multisampled = true,
+                Some("sampler") if meta.path.is_ident("comparison") => {
+                    sampler = quote! { wgpu::SamplerBindingType::Comparison }
+                }
+                Some("sampler") if meta.path.is_ident("non_filtering") => {
+                    sampler = quote! { wgpu::SamplerBindingType::NonFiltering }
+                }
+                Some("storage") => {
+                    return Err(meta.error(
+                        "unsupported storage attribute, expected read_only, binding or visibility",
+                    ))
+                }
+                Some("texture") => return Err(meta.error(
+                    "unsupported texture attribute, expected sample_type, dimension, multisampled, binding or visibility",
+                )),
+                Some("sampler") => return Err(meta.error(
+                    "unsupported sampler attribute, expected comparison, non_filtering, binding or visibility",
+                )),
+                _ => {
+                    return Err(
+                        meta.error("unsupported uniform attribute, expected binding or visibility")
+                    )
+                }
+            }
+            Ok(())
+        })?;
+    } else if !matches!(attr.meta, syn::Meta::Path(_)) {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "resource attributes take a list of options, as in #[storage(read_only)]",
+        ));
+    }
+
+    let resource = match kind.as_deref() {
+        Some("uniform") => Resource::Buffer(quote! { wgpu::BufferBindingType::Uniform }),
+        Some("storage") => Resource::Buffer(quote! {
+            wgpu::BufferBindingType::Storage { read_only: #read_only }
+        }),
+        Some("texture") => Resource::Texture {
+            sample_type,
+            dimension,
+            multisampled,
+        },
+        _ => Resource::Sampler(sampler),
     };
+    Ok(BindingOptions {
+        resource,
+        binding,
+        visibility,
+    })
+}
 
-    gen.into()
+/// The wgpu::ShaderStages of a visibility such as "vertex | fragment"
+fn parse_visibility(value: syn::LitStr) -> syn::Result<TokenStream2> {
+    let mut stages = Vec::new();
+    for stage in value.value().split('|').map(str::trim) {
+        stages.push(match stage {
+            "vertex" => quote! { wgpu::ShaderStages::VERTEX },
+            "fragment" => quote! { wgpu::ShaderStages::FRAGMENT },
+            "compute" => quote! { wgpu::ShaderStages::COMPUTE },
+            _ => {
+                return Err(syn::Error::new(
+                    value.span(),
+                    format!("unknown shader stage {stage:?}, expected vertex, fragment or compute"),
+                ))
+            }
+        });
+    }
+    // ShaderStages::union is const, so the entries can still be evaluated at compile time
+    let first = stages.remove(0);
+    Ok(quote! { #first #(.union(#stages))* })
+}
+
+/// The wgpu::TextureSampleType of a sample_type such as "unfilterable_float"
+fn parse_sample_type(value: syn::LitStr) -> syn::Result<TokenStream2> {
+    Ok(match value.value().as_str() {
+        "float" => quote! { wgpu::TextureSampleType::Float { filterable: true } },
+        "unfilterable_float" => quote! { wgpu::TextureSampleType::Float { filterable: false } },
+        "depth" => quote! { wgpu::TextureSampleType::Depth },
+        "sint" => quote! { wgpu::TextureSampleType::Sint },
+        "uint" => quote! { wgpu::TextureSampleType::Uint },
+        other => {
+            return Err(syn::Error::new(
+                value.span(),
+                format!(
+                    "unknown sample type {other:?}, expected float, unfilterable_float, depth, sint or uint"
+                ),
+            ))
+        }
+    })
+}
+
+/// Converts a struct name such as FrameBindings into frame_bindings, for labels
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// The name of a field in WGSL, tuple struct fields being named after their index
+fn member_name(f: &syn::Field, i: usize) -> String {
+    match &f.ident {
+        Some(ident) => ident.unraw().to_string(),
+        None => format!("field_{i}"),
+    }
+}
+
+/// The wgsl::Member of every field of a uniform struct, adding a WgslType bound
+/// on the field types depending on its generic parameters
+fn uniform_members(
+    ast: &syn::DeriveInput,
+    fields: &Punctuated<syn::Field, syn::Token![,]>,
+    bounds: &mut Vec<syn::WherePredicate>,
+) -> Vec<TokenStream2> {
+    let name = &ast.ident;
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+    let generic_params = generic_params(&ast.generics);
+    let mut members = Vec::new();
+    for (i, f) in fields.iter().enumerate() {
+        let ty = &f.ty;
+        let member_name = member_name(f, i);
+        let member = match &f.ident {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(syn::Index::from(i)),
+        };
+        if mentions(ty.to_token_stream(), &generic_params) {
+            bounds.push(syn::parse_quote! { #ty: ::wgpu_utils::WgslType });
+        }
+        members.push(quote_spanned! {ty.span()=>
+            ::wgpu_utils::wgsl::Member::of::<#ty>(
+                #member_name,
+                ::core::mem::offset_of!(#name #ty_generics, #member),
+            )
+        });
+    }
+    members
+}
+
+/// Derives the ```UniformLayout``` trait
+///
+/// Lists the WGSL type and offset of every field, which need to implement the
+/// ```WgslType``` trait, so the layout is checked against the WGSL rules for uniforms
+/// at compile time. Members need to be at offsets aligned as in WGSL, a vec3 or vec4
+/// taking 16 bytes, and the struct padded to a multiple of 16 bytes, so it should
+/// be ```#[repr(C)]```. Structs without generic parameters are checked where they're
+/// declared, generic ones where ```UniformLayout::CHECK``` is used, as by UniformBuffer.
+///
+/// Example
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable, UniformLayout)]
+/// struct Light {
+///     direction: [f32; 3],
+///     // Fills the end of the vec3, placing it first fails to compile as WGSL
+///     // aligns the vec3 after it to 16 bytes
+///     intensity: f32,
+///     color: [f32; 4],
+/// }
+/// ```
+#[proc_macro_derive(UniformLayout)]
+pub fn uniform_layout_derive(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    impl_uniform_layout(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn impl_uniform_layout(ast: &syn::DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &ast.ident;
+    let fields = struct_fields(ast, "UniformLayout")?;
+    if fields.is_empty() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "#[derive(UniformLayout)] needs at least one field in the layout",
+        ));
+    }
+    let mut bounds: Vec<syn::WherePredicate> = Vec::new();
+    let members = uniform_members(ast, fields, &mut bounds);
+
+    // Generic structs can only be checked once their parameters are known
+    let check = if ast.generics.params.is_empty() {
+        quote_spanned! {name.span()=>
+            const _: () = <#name as ::wgpu_utils::UniformLayout>::CHECK;
+        }
+    } else {
+        TokenStream2::new()
+    };
+
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+    let mut generics = ast.generics.clone();
+    if !bounds.is_empty() {
+        generics.make_where_clause().predicates.extend(bounds);
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::wgpu_utils::UniformLayout for #name #ty_generics #where_clause {
+            const MEMBERS: &'static [::wgpu_utils::wgsl::Member] = &[#(#members),*];
+        }
+
+        #check
+    })
+}
+
+/// Whether the struct level #[wgsl(...)] attributes declare a vertex input
+fn wgsl_options(attrs: &[syn::Attribute]) -> syn::Result<bool> {
+    let mut vertex = false;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("wgsl")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("vertex") {
+                vertex = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported struct attribute, expected vertex"))
+            }
+        })?;
+    }
+    Ok(vertex)
+}
+
+/// Options of the struct level #[vertex(...)] attributes
+#[derive(Default)]
+struct StructOptions {
+    start_location: u32,
+    instance: bool,
+}
+
+fn struct_options(attrs: &[syn::Attribute]) -> syn::Result<StructOptions> {
+    let mut options = StructOptions::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("vertex")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("start_location") {
+                options.start_location = parse_location(&meta)?;
+                Ok(())
+            } else if meta.path.is_ident("instance") {
+                options.instance = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported struct attribute, expected start_location or instance"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+/// Options of the field level #[vertex(...)] attributes
+#[derive(Default)]
+struct FieldOptions {
+    location: Option<u32>,
+    format: Option<syn::Ident>,
+    skip: bool,
+}
+
+fn field_options(attrs: &[syn::Attribute]) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("vertex")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("location") {
+                options.location = Some(parse_location(&meta)?);
+            } else if meta.path.is_ident("format") {
+                // Named after the wgpu::VertexFormat variant, spanning the literal so
+                // an unknown format is reported there
+                let value: syn::LitStr = meta.value()?.parse()?;
+                options.format = Some(value.parse()?);
+            } else if meta.path.is_ident("skip") {
+                options.skip = true;
+            } else {
+                return Err(
+                    meta.error("unsupported field attribute, expected location, format or skip")
+                );
+            }
+            Ok(())
+        })?;
+    }
+    Ok(options)
+}
+
+fn parse_location(meta: &syn::meta::ParseNestedMeta) -> syn::Result<u32> {
+    let value: syn::LitInt = meta.value()?.parse()?;
+    value
+        .base10_parse()
+        .map_err(|_| syn::Error::new(value.span(), "shader locations must be unsigned integers"))
+}
+
+/// Whether any of the identifiers appears in the tokens, such as a generic
+/// parameter in a field type
+fn mentions(tokens: TokenStream2, idents: &[&syn::Ident]) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(ident) => idents.contains(&&ident),
+        proc_macro2::TokenTree::Group(group) => mentions(group.stream(), idents),
+        _ => false,
+    })
+}
+
+/// The column type and count of arrays of arrays, such as [[f32; 4]; 4]
+fn matrix_columns(ty: &syn::Type) -> syn::Result<Option<(&syn::Type, usize)>> {
+    let syn::Type::Array(matrix) = ty else {
+        return Ok(None);
+    };
+    if !matches!(*matrix.elem, syn::Type::Array(_)) {
+        return Ok(None);
+    }
+    match &matrix.len {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(count),
+            ..
+        }) => Ok(Some((&matrix.elem, count.base10_parse()?))),
+        len => Err(syn::Error::new_spanned(
+            len,
+            "matrix column counts must be integer literals",
+        )),
+    }
 }